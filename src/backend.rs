@@ -0,0 +1,246 @@
+use crate::{cell::Cell, element::CellId, life::Rule};
+use std::{error, fmt, mem};
+
+// Matches the default `max_storage_buffer_binding_size` of 128 MiB.
+pub const MAX_CELL_COUNT: u64 = (128 << 20) / mem::size_of::<Cell>() as u64;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimulationDescriptor {
+    pub size: [u32; 2],
+    pub seed: u32,
+    pub force_fallback_adapter: bool,
+    pub mode: Mode,
+}
+
+impl Default for SimulationDescriptor {
+    fn default() -> Self {
+        Self {
+            size: [128, 128],
+            seed: 0,
+            force_fallback_adapter: false,
+            mode: Mode::default(),
+        }
+    }
+}
+
+/// Which rules every step runs over the cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// The falling-sand rules, including heat.
+    #[default]
+    Sand,
+    /// Only a Life-like generation of `CellId::Life` cells, leaving every other cell untouched.
+    Life(Rule),
+    /// A Life-like generation followed by the falling-sand rules.
+    SandAndLife(Rule),
+}
+
+impl Mode {
+    pub fn sand(self) -> bool {
+        !matches!(self, Self::Life(_))
+    }
+
+    pub fn life_rule(self) -> Option<Rule> {
+        match self {
+            Self::Sand => None,
+            Self::Life(rule) | Self::SandAndLife(rule) => Some(rule),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sand => write!(f, "sand"),
+            Self::Life(rule) => write!(f, "life {rule}"),
+            Self::SandAndLife(rule) => write!(f, "sand and life {rule}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Brush {
+    /// Paints cells of an element at the element's spawn temperature.
+    Cell(CellId),
+    /// Sets the temperature of the cells under the cursor, in degrees Celsius.
+    Temperature(f32),
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self::Cell(CellId::Void)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Anchor {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+impl Anchor {
+    fn offset(self, old_length: u32, new_length: u32) -> i64 {
+        let difference = new_length as i64 - old_length as i64;
+        match self {
+            Self::Start => 0,
+            Self::Center => difference / 2,
+            Self::End => difference,
+        }
+    }
+}
+
+/// A simulation backend.
+///
+/// Every backend is deterministic: starting from the same seed and initial cells, the same
+/// sequence of calls produces the same cells on every run and on every backend. The RNG is a
+/// counter that starts at `hash_u32(seed)` and advances once per checkerboard pass and once per
+/// Life generation, so saving `state()` and restoring it with `set_state()` resumes the exact
+/// same random sequence.
+pub trait Backend {
+    fn size(&self) -> [u32; 2];
+
+    /// Fails without changing the world if it would be empty or too large for the backend.
+    fn resize(&mut self, size: [u32; 2], anchor: [Anchor; 2]) -> Result<(), SizeError>;
+
+    fn set_cursor(&mut self, enabled: bool, radius: u32, position: [u32; 2], brush: Brush);
+
+    fn mode(&self) -> Mode;
+
+    fn set_mode(&mut self, mode: Mode);
+
+    fn step(&mut self);
+
+    fn state(&self) -> u32;
+
+    fn set_state(&mut self, state: u32);
+
+    fn read_cells(&self) -> Vec<Cell>;
+
+    fn write_cells(&mut self, cells: &[Cell]);
+
+    /// Reads the temperature of every cell, in the fixed point units of `element::temperature`.
+    fn read_temperatures(&self) -> Vec<i32>;
+
+    fn write_temperatures(&mut self, temperatures: &[i32]);
+}
+
+/// A world size that is empty or too large for a backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeError(pub [u32; 2]);
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [width, height] = self.0;
+        write!(f, "unsupported world size {width}x{height}")
+    }
+}
+
+impl error::Error for SizeError {}
+
+/// Fails if a world of `size` would be empty or have more than `MAX_CELL_COUNT` cells.
+pub fn check_size(size: [u32; 2]) -> Result<(), SizeError> {
+    if size.contains(&0) || size[0] as u64 * size[1] as u64 > MAX_CELL_COUNT {
+        return Err(SizeError(size));
+    }
+    Ok(())
+}
+
+pub fn resize_grid<T: Copy + Default>(
+    values: &[T],
+    old_size: [u32; 2],
+    new_size: [u32; 2],
+    anchor: [Anchor; 2],
+) -> Vec<T> {
+    let offset = [
+        anchor[0].offset(old_size[0], new_size[0]),
+        anchor[1].offset(old_size[1], new_size[1]),
+    ];
+    let mut resized_values = vec![T::default(); (new_size[0] * new_size[1]) as usize];
+    for y in 0..new_size[1] {
+        let old_y = y as i64 - offset[1];
+        if old_y < 0 || old_y >= old_size[1] as i64 {
+            continue;
+        }
+        for x in 0..new_size[0] {
+            let old_x = x as i64 - offset[0];
+            if old_x < 0 || old_x >= old_size[0] as i64 {
+                continue;
+            }
+            resized_values[(y * new_size[0] + x) as usize] =
+                values[(old_y as u32 * old_size[0] + old_x as u32) as usize];
+        }
+    }
+    resized_values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rows from the bottom up.
+    const GRID: [u32; 6] = [1, 2, 3, 4, 5, 6];
+    const GRID_SIZE: [u32; 2] = [3, 2];
+
+    #[test]
+    fn resizing_grows_around_the_anchor() {
+        let grow = |anchor| resize_grid(&GRID, GRID_SIZE, [5, 4], [anchor; 2]);
+        #[rustfmt::skip]
+        let expected = [
+            (Anchor::Start, [
+                1, 2, 3, 0, 0,
+                4, 5, 6, 0, 0,
+                0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0,
+            ]),
+            (Anchor::Center, [
+                0, 0, 0, 0, 0,
+                0, 1, 2, 3, 0,
+                0, 4, 5, 6, 0,
+                0, 0, 0, 0, 0,
+            ]),
+            (Anchor::End, [
+                0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0,
+                0, 0, 1, 2, 3,
+                0, 0, 4, 5, 6,
+            ]),
+        ];
+        for (anchor, grid) in expected {
+            assert_eq!(grow(anchor), grid, "{anchor:?}");
+        }
+        #[rustfmt::skip]
+        let mixed = [
+            0, 0, 0, 0,
+            1, 2, 3, 0,
+            4, 5, 6, 0,
+        ];
+        assert_eq!(
+            resize_grid(&GRID, GRID_SIZE, [4, 3], [Anchor::Start, Anchor::End]),
+            mixed
+        );
+    }
+
+    #[test]
+    fn resizing_crops_around_the_anchor() {
+        for (anchor, grid) in [
+            (Anchor::Start, [1, 2, 4, 5]),
+            (Anchor::Center, [1, 2, 4, 5]),
+            (Anchor::End, [2, 3, 5, 6]),
+        ] {
+            assert_eq!(
+                resize_grid(&GRID, GRID_SIZE, [2, 2], [anchor; 2]),
+                grid,
+                "{anchor:?}"
+            );
+        }
+        for (anchor, value) in [(Anchor::Start, 1), (Anchor::Center, 2), (Anchor::End, 6)] {
+            assert_eq!(
+                resize_grid(&GRID, GRID_SIZE, [1, 1], [anchor; 2]),
+                [value],
+                "{anchor:?}"
+            );
+        }
+    }
+}
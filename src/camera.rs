@@ -0,0 +1,129 @@
+pub const MIN_ZOOM: f32 = 1.0;
+pub const MAX_ZOOM: f32 = 64.0;
+
+/// The region of the world shown in the window.
+///
+/// View positions go from `[0.0, 0.0]` at the bottom left corner of the window to `[1.0, 1.0]` at
+/// the top right one, and world positions are in cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// World position at the bottom left corner of the view.
+    pub offset: [f32; 2],
+    /// How many times the world is magnified, so a zoom of 1 shows the whole world.
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            zoom: MIN_ZOOM,
+        }
+    }
+}
+
+impl Camera {
+    pub fn view_size(&self, world_size: [u32; 2]) -> [f32; 2] {
+        world_size.map(|length| length as f32 / self.zoom)
+    }
+
+    pub fn world_position(&self, world_size: [u32; 2], view_position: [f32; 2]) -> [f32; 2] {
+        let view_size = self.view_size(world_size);
+        [
+            self.offset[0] + view_position[0] * view_size[0],
+            self.offset[1] + view_position[1] * view_size[1],
+        ]
+    }
+
+    /// Returns the cell at a view position, or `None` outside the world.
+    pub fn cell_position(&self, world_size: [u32; 2], view_position: [f32; 2]) -> Option<[u32; 2]> {
+        let world_position = self.world_position(world_size, view_position);
+        let contains = (0..2).all(|axis| {
+            world_position[axis] >= 0.0 && world_position[axis] < world_size[axis] as f32
+        });
+        contains.then(|| world_position.map(|value| value as u32))
+    }
+
+    /// Multiplies the zoom by `factor` while keeping the world position under `view_position` in
+    /// place.
+    pub fn zoom_at(&mut self, world_size: [u32; 2], view_position: [f32; 2], factor: f32) {
+        let anchor = self.world_position(world_size, view_position);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let view_size = self.view_size(world_size);
+        self.offset = [
+            anchor[0] - view_position[0] * view_size[0],
+            anchor[1] - view_position[1] * view_size[1],
+        ];
+        *self = self.clamped(world_size);
+    }
+
+    /// Moves the world along with a drag of `view_delta`.
+    pub fn pan(&mut self, world_size: [u32; 2], view_delta: [f32; 2]) {
+        let view_size = self.view_size(world_size);
+        self.offset[0] -= view_delta[0] * view_size[0];
+        self.offset[1] -= view_delta[1] * view_size[1];
+        *self = self.clamped(world_size);
+    }
+
+    /// Keeps the view inside the world.
+    pub fn clamped(self, world_size: [u32; 2]) -> Self {
+        let zoom = self.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let view_size = world_size.map(|length| length as f32 / zoom);
+        Self {
+            offset: [
+                self.offset[0].clamp(0.0, world_size[0] as f32 - view_size[0]),
+                self.offset[1].clamp(0.0, world_size[1] as f32 - view_size[1]),
+            ],
+            zoom,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD_SIZE: [u32; 2] = [100, 50];
+
+    #[test]
+    fn zooming_keeps_the_cell_under_the_cursor() {
+        let mut camera = Camera::default();
+        let view_position = [0.3, 0.7];
+        let cell = camera.cell_position(WORLD_SIZE, view_position);
+        assert_eq!(cell, Some([30, 35]));
+        for factor in [2.0, 1.5, 4.0, 0.5] {
+            camera.zoom_at(WORLD_SIZE, view_position, factor);
+            assert_eq!(camera.cell_position(WORLD_SIZE, view_position), cell);
+        }
+        assert_eq!(camera.zoom, 6.0);
+        camera.zoom_at(WORLD_SIZE, view_position, 1000.0);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+        camera.zoom_at(WORLD_SIZE, view_position, 0.001);
+        assert_eq!(camera, Camera::default());
+    }
+
+    #[test]
+    fn panning_stops_at_the_edges() {
+        let mut camera = Camera::default();
+        camera.pan(WORLD_SIZE, [0.5, -0.5]);
+        assert_eq!(camera, Camera::default());
+        camera.zoom_at(WORLD_SIZE, [0.0, 0.0], 4.0);
+        camera.pan(WORLD_SIZE, [-0.5, -0.5]);
+        assert_eq!(camera.offset, [12.5, 6.25]);
+        camera.pan(WORLD_SIZE, [-10.0, 10.0]);
+        assert_eq!(camera.offset, [75.0, 0.0]);
+        assert_eq!(
+            camera.cell_position(WORLD_SIZE, [0.99, 0.99]),
+            Some([99, 12])
+        );
+    }
+
+    #[test]
+    fn positions_outside_the_world_have_no_cell() {
+        let camera = Camera::default();
+        assert_eq!(camera.cell_position(WORLD_SIZE, [0.0, 0.0]), Some([0, 0]));
+        for view_position in [[-0.01, 0.5], [0.5, -0.01], [1.0, 0.5], [0.5, 1.0]] {
+            assert_eq!(camera.cell_position(WORLD_SIZE, view_position), None);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::element::CellId;
+
+/// The top bits of `Cell.state` hold a shade that is picked on spawn and never changes, so a cell
+/// keeps its colour as it moves. The bits in `STATE_MASK` hold the cell's lifetime, growth stage or
+/// the random value that changes every step.
+pub const SHADE_SHIFT: u32 = 24;
+pub const STATE_MASK: u32 = (1 << SHADE_SHIFT) - 1;
+
+/// Velocities are stored in sixteenths of a cell per step.
+pub const VELOCITY_SCALE: u32 = 16;
+/// Velocity gained by every step spent falling.
+pub const GRAVITY: u32 = 4;
+/// Every step moves fast cells in this many rounds of checkerboard passes, one cell per round.
+pub const SUBSTEPS: u32 = 4;
+pub const MAX_VELOCITY: u32 = (SUBSTEPS - 1) * VELOCITY_SCALE;
+/// Cells landing at least this fast scatter sideways.
+pub const SPLASH_VELOCITY: u32 = 2 * VELOCITY_SCALE;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cell {
+    pub id: CellId,
+    pub state: u32,
+    pub velocity: u32,
+}
+
+impl Cell {
+    // Mirrors `cell_spawn` in `simulation.wgsl`: cells with a lifetime start with a random
+    // remaining lifetime between half and all of it, growing cells start at their full growth
+    // stage and other cells keep the random value. Every cell spawns at rest.
+    pub fn spawn(id: CellId, random: u32) -> Self {
+        let element = id.element();
+        let shade = random & !STATE_MASK;
+        if element.lifetime != 0 {
+            return Self {
+                id,
+                state: shade | (element.lifetime / 2 + random % (element.lifetime / 2 + 1)),
+                velocity: 0,
+            };
+        }
+        if element.growth != 0 {
+            return Self {
+                id,
+                state: shade | element.growth,
+                velocity: 0,
+            };
+        }
+        Self {
+            id,
+            state: random,
+            velocity: 0,
+        }
+    }
+
+    pub fn shade(self) -> u32 {
+        self.state >> SHADE_SHIFT
+    }
+}
+
+pub fn hash_u32(value: u32) -> u32 {
+    let mut x = value;
+    x = x.wrapping_add(x << 10);
+    x ^= x >> 6;
+    x = x.wrapping_add(x << 3);
+    x ^= x >> 11;
+    x = x.wrapping_add(x << 15);
+    x
+}
+
+pub fn hash_vec2_u32(value: [u32; 2]) -> u32 {
+    hash_u32(value[0] ^ hash_u32(value[1]))
+}
@@ -0,0 +1,1068 @@
+use crate::{
+    backend::{self, Anchor, Backend, Brush, Mode, SimulationDescriptor, SizeError},
+    cell::{self, hash_u32, hash_vec2_u32, Cell},
+    element::{self, Behaviour, CellId},
+    life::Rule,
+};
+
+const NEIGHBOUR_OFFSETS: [[u32; 2]; 8] = [
+    [u32::MAX, u32::MAX],
+    [0, u32::MAX],
+    [1, u32::MAX],
+    [u32::MAX, 0],
+    [1, 0],
+    [u32::MAX, 1],
+    [0, 1],
+    [1, 1],
+];
+
+const HEAT_OFFSETS: [[u32; 2]; 4] = [[u32::MAX, 0], [1, 0], [0, u32::MAX], [0, 1]];
+
+const PAIRING_OFFSETS: [[u32; 2]; 4] = [[1, 0], [0, 1], [1, 1], [1, u32::MAX]];
+
+#[derive(Clone, Copy, Debug)]
+struct Cursor {
+    enabled: bool,
+    radius: u32,
+    position: [u32; 2],
+    brush: Brush,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 1,
+            position: [0, 0],
+            brush: Brush::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Push {
+    local_offset: [u32; 2],
+    state: u32,
+    substep: u32,
+    life_rule: [u32; 2],
+    pairing: u32,
+}
+
+// Mirrors `simulation.wgsl` step for step, so both backends produce identical grids.
+pub struct CpuSimulation {
+    size: [u32; 2],
+    cursor: Cursor,
+    cells_input: Vec<Cell>,
+    cells_output: Vec<Cell>,
+    temperatures_input: Vec<i32>,
+    temperatures_output: Vec<i32>,
+    mode: Mode,
+    state: u32,
+}
+
+impl CpuSimulation {
+    pub fn new(descriptor: &SimulationDescriptor) -> Result<Self, SizeError> {
+        let size = descriptor.size;
+        backend::check_size(size)?;
+        let cell_count = (size[0] * size[1]) as usize;
+        let ambient_temperature = element::temperature(element::AMBIENT_TEMPERATURE);
+        Ok(Self {
+            size,
+            cursor: Cursor::default(),
+            cells_input: vec![Cell::default(); cell_count],
+            cells_output: vec![Cell::default(); cell_count],
+            temperatures_input: vec![ambient_temperature; cell_count],
+            temperatures_output: vec![ambient_temperature; cell_count],
+            mode: descriptor.mode,
+            state: hash_u32(descriptor.seed),
+        })
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    pub fn resize(&mut self, size: [u32; 2], anchor: [Anchor; 2]) -> Result<(), SizeError> {
+        backend::check_size(size)?;
+        self.cells_output = backend::resize_grid(&self.cells_output, self.size, size, anchor);
+        self.cells_input = vec![Cell::default(); self.cells_output.len()];
+        self.temperatures_output =
+            backend::resize_grid(&self.temperatures_output, self.size, size, anchor);
+        self.temperatures_input = vec![0; self.temperatures_output.len()];
+        self.size = size;
+        Ok(())
+    }
+
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: u32) {
+        self.state = state;
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells_output
+    }
+
+    pub fn write_cells(&mut self, cells: &[Cell]) {
+        self.cells_output.copy_from_slice(cells);
+    }
+
+    pub fn temperatures(&self) -> &[i32] {
+        &self.temperatures_output
+    }
+
+    pub fn write_temperatures(&mut self, temperatures: &[i32]) {
+        self.temperatures_output.copy_from_slice(temperatures);
+    }
+
+    pub fn set_cursor(&mut self, enabled: bool, radius: u32, position: [u32; 2], brush: Brush) {
+        self.cursor = Cursor {
+            enabled,
+            radius,
+            position,
+            brush,
+        };
+        for y in 0..self.size[1] {
+            for x in 0..self.size[0] {
+                self.compute_cursor([x, y]);
+            }
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn step(&mut self) {
+        if let Some(rule) = self.mode.life_rule() {
+            self.step_life(rule);
+        }
+        if self.mode.sand() {
+            self.step_sand();
+        }
+    }
+
+    fn step_life(&mut self, rule: Rule) {
+        self.cells_input.copy_from_slice(&self.cells_output);
+        let push = Push {
+            state: hash_u32(self.state),
+            life_rule: [rule.birth, rule.survival],
+            ..Default::default()
+        };
+        for y in 0..self.size[1] {
+            for x in 0..self.size[0] {
+                self.compute_life(&push, [x, y]);
+            }
+        }
+        self.state = self.state.wrapping_add(1);
+    }
+
+    fn step_sand(&mut self) {
+        let workgroups = [self.size[0].div_ceil(3), self.size[1].div_ceil(3)];
+        self.temperatures_input
+            .copy_from_slice(&self.temperatures_output);
+        for substep in 0..cell::SUBSTEPS {
+            let pairing = hash_u32(self.state);
+            self.cells_input.copy_from_slice(&self.cells_output);
+            self.cells_output.fill(Cell::default());
+            if substep == 0 {
+                for y in 0..self.size[1] {
+                    for x in 0..self.size[0] {
+                        self.compute_heat([x, y]);
+                    }
+                }
+            }
+            for i in 0..9 {
+                let push = Push {
+                    local_offset: [i % 3, i / 3],
+                    state: hash_u32(self.state),
+                    substep,
+                    pairing,
+                    ..Default::default()
+                };
+                for y in 0..workgroups[1] {
+                    for x in 0..workgroups[0] {
+                        self.compute_step(&push, [x, y]);
+                    }
+                }
+                self.state = self.state.wrapping_add(1);
+            }
+        }
+    }
+
+    fn world_contains(&self, position: [u32; 2]) -> bool {
+        position[0] < self.size[0] && position[1] < self.size[1]
+    }
+
+    fn cursor_squared_distance(&self, position: [u32; 2]) -> u32 {
+        let displacement = [
+            self.cursor.position[0].wrapping_sub(position[0]),
+            self.cursor.position[1].wrapping_sub(position[1]),
+        ];
+        displacement[0]
+            .wrapping_mul(displacement[0])
+            .wrapping_add(displacement[1].wrapping_mul(displacement[1]))
+    }
+
+    fn cursor_contains(&self, position: [u32; 2]) -> bool {
+        self.cursor_squared_distance(position) < self.cursor.radius.wrapping_mul(self.cursor.radius)
+    }
+
+    fn cell_index(&self, position: [u32; 2]) -> usize {
+        (position[1] * self.size[0] + position[0]) as usize
+    }
+
+    fn compute_cursor(&mut self, position: [u32; 2]) {
+        if !self.world_contains(position) || !self.cursor.enabled || !self.cursor_contains(position)
+        {
+            return;
+        }
+        let index = self.cell_index(position);
+        let cell_id = match self.cursor.brush {
+            Brush::Cell(cell_id) => cell_id,
+            Brush::Temperature(celsius) => {
+                self.temperatures_output[index] = element::temperature(celsius);
+                return;
+            }
+        };
+        let state =
+            hash_u32(self.state) ^ hash_vec2_u32(self.cursor.position) ^ hash_vec2_u32(position);
+        self.cells_output[index] = Cell::spawn(cell_id, state);
+        self.temperatures_output[index] = element::temperature(cell_id.element().temperature);
+    }
+
+    fn compute_heat(&mut self, position: [u32; 2]) {
+        let index = self.cell_index(position);
+        let id = self.cells_input[index].id;
+        let ambient_temperature = element::temperature(CellId::Void.element().temperature);
+        if id == CellId::Void {
+            self.temperatures_output[index] = ambient_temperature;
+            return;
+        }
+        let temperature = self.temperatures_input[index];
+        let mut heat = 0;
+        for offset in HEAT_OFFSETS {
+            let neighbour_position = [
+                position[0].wrapping_add(offset[0]),
+                position[1].wrapping_add(offset[1]),
+            ];
+            if !self.world_contains(neighbour_position) {
+                continue;
+            }
+            let neighbour_index = self.cell_index(neighbour_position);
+            let neighbour_id = self.cells_input[neighbour_index].id;
+            let neighbour_temperature = if neighbour_id == CellId::Void {
+                ambient_temperature
+            } else {
+                self.temperatures_input[neighbour_index]
+            };
+            let conductivity = element::conductivity(id.element().conductivity)
+                .min(element::conductivity(neighbour_id.element().conductivity));
+            heat += (conductivity * (neighbour_temperature - temperature)) / 1024;
+        }
+        self.temperatures_output[index] = temperature + heat;
+    }
+
+    fn compute_life(&mut self, push: &Push, position: [u32; 2]) {
+        let index = self.cell_index(position);
+        let cell = self.cells_input[index];
+        let live_neighbours = NEIGHBOUR_OFFSETS
+            .iter()
+            .filter(|offset| {
+                let neighbour_position = [
+                    position[0].wrapping_add(offset[0]),
+                    position[1].wrapping_add(offset[1]),
+                ];
+                self.world_contains(neighbour_position)
+                    && self.cells_input[self.cell_index(neighbour_position)].id == CellId::Life
+            })
+            .count();
+        let random = hash_u32(push.state ^ hash_vec2_u32(position));
+        if cell.id == CellId::Life && push.life_rule[1] & (1 << live_neighbours) == 0 {
+            self.cells_output[index] = Cell::spawn(CellId::Void, random);
+            return;
+        }
+        if cell.id == CellId::Void && push.life_rule[0] & (1 << live_neighbours) != 0 {
+            self.cells_output[index] = Cell::spawn(CellId::Life, random);
+            self.temperatures_output[index] =
+                element::temperature(CellId::Life.element().temperature);
+            return;
+        }
+        self.cells_output[index] = cell;
+    }
+
+    fn cell_processed(&self, push: &Push, position: [u32; 2]) -> bool {
+        let pass_index = push.local_offset[0] + push.local_offset[1] * 3;
+        (position[0] % 3) + (position[1] % 3) * 3 < pass_index
+    }
+
+    fn cell_current(&self, push: &Push, position: [u32; 2]) -> Cell {
+        let index = self.cell_index(position);
+        if self.cell_processed(push, position) || self.cells_output[index].id != CellId::Void {
+            return self.cells_output[index];
+        }
+        self.cells_input[index]
+    }
+
+    fn cell_compare(&self, push: &Push, position: [u32; 2], id: CellId) -> bool {
+        let other = self.cell_current(push, position).id.element();
+        if other.id == CellId::Void {
+            return true;
+        }
+        if !other.behaviour.movable() {
+            return false;
+        }
+        if id.element().behaviour == Behaviour::Gas {
+            return other.behaviour != Behaviour::Gas && other.density > id.element().density;
+        }
+        other.density < id.element().density
+    }
+
+    fn cell_swap(
+        &mut self,
+        push: &Push,
+        from_position: [u32; 2],
+        from_cell: Cell,
+        to_position: [u32; 2],
+    ) {
+        let from_index = self.cell_index(from_position);
+        let mut s = push.state;
+        let mut moved_cell = from_cell;
+        s ^= hash_vec2_u32(to_position);
+        if !moved_cell.id.element().keeps_state() {
+            moved_cell.state ^= s & cell::STATE_MASK;
+        }
+        let to_index = self.cell_index(to_position);
+        let mut to_cell = self.cell_current(push, to_position);
+        let mut to_temperature = self.temperatures_output[to_index];
+        if push.substep == 0
+            && !self.cell_processed(push, to_position)
+            && self.cells_output[to_index].id == CellId::Void
+        {
+            let product = self.cell_reaction(push, to_position, to_cell.id);
+            if product != to_cell.id {
+                to_cell = Cell::spawn(product, hash_u32(push.state ^ hash_vec2_u32(to_position)));
+                to_temperature = element::temperature(product.element().temperature);
+            }
+        }
+        s ^= hash_vec2_u32(from_position);
+        if !to_cell.id.element().keeps_state() {
+            to_cell.state ^= s & cell::STATE_MASK;
+        }
+        to_cell.velocity = 0;
+        self.cells_output[to_index] = moved_cell;
+        self.cells_output[from_index] = to_cell;
+        self.temperatures_output[to_index] = self.temperatures_output[from_index];
+        self.temperatures_output[from_index] = to_temperature;
+    }
+
+    fn cell_ignited(&self, position: [u32; 2], id: CellId, random: u32) -> bool {
+        let flammability = element::chance(id.element().flammability);
+        if flammability == 0 || (random & 0xffff) >= flammability {
+            return false;
+        }
+        NEIGHBOUR_OFFSETS.iter().any(|offset| {
+            let neighbour_position = [
+                position[0].wrapping_add(offset[0]),
+                position[1].wrapping_add(offset[1]),
+            ];
+            self.world_contains(neighbour_position)
+                && self.cells_input[self.cell_index(neighbour_position)]
+                    .id
+                    .element()
+                    .ignites
+        })
+    }
+
+    fn cell_partner(&self, push: &Push, position: [u32; 2]) -> [u32; 2] {
+        let axis = push.pairing % 4;
+        let offset = PAIRING_OFFSETS[axis as usize];
+        let coordinate = if axis == 1 { position[1] } else { position[0] };
+        if (coordinate ^ (push.pairing >> 2)) & 1 == 0 {
+            [
+                position[0].wrapping_add(offset[0]),
+                position[1].wrapping_add(offset[1]),
+            ]
+        } else {
+            [
+                position[0].wrapping_sub(offset[0]),
+                position[1].wrapping_sub(offset[1]),
+            ]
+        }
+    }
+
+    fn cell_reaction(&self, push: &Push, position: [u32; 2], id: CellId) -> CellId {
+        let partner_position = self.cell_partner(push, position);
+        if !self.world_contains(partner_position) {
+            return id;
+        }
+        let partner_id = self.cells_input[self.cell_index(partner_position)].id;
+        if partner_id == CellId::Void {
+            return id;
+        }
+        if id.element().reacts_with == partner_id {
+            return id.element().reacts_into;
+        }
+        if partner_id.element().reacts_with == id {
+            return partner_id.element().reactant_into;
+        }
+        id
+    }
+
+    fn cell_pair_random(&self, position: [u32; 2], neighbour_position: [u32; 2]) -> u32 {
+        let state = self.cells_input[self.cell_index(position)].state
+            ^ self.cells_input[self.cell_index(neighbour_position)].state;
+        hash_u32(state ^ hash_vec2_u32(position) ^ hash_vec2_u32(neighbour_position))
+    }
+
+    fn cell_dissolved(&self, position: [u32; 2], id: CellId) -> bool {
+        let corrosive = id.element().corrosive;
+        NEIGHBOUR_OFFSETS.iter().any(|offset| {
+            let neighbour_position = [
+                position[0].wrapping_add(offset[0]),
+                position[1].wrapping_add(offset[1]),
+            ];
+            if !self.world_contains(neighbour_position) {
+                return false;
+            }
+            let neighbour_id = self.cells_input[self.cell_index(neighbour_position)].id;
+            if corrosive == neighbour_id.element().corrosive {
+                return false;
+            }
+            let resistance = if corrosive {
+                neighbour_id.element().acid_resistance
+            } else {
+                id.element().acid_resistance
+            };
+            (self.cell_pair_random(position, neighbour_position) & 0xffff)
+                < element::chance(1.0 - resistance)
+        })
+    }
+
+    fn cell_germinates(&self, position: [u32; 2], id: CellId) -> bool {
+        if id.element().germinates_into == CellId::Void {
+            return false;
+        }
+        NEIGHBOUR_OFFSETS.iter().any(|offset| {
+            let neighbour_position = [
+                position[0].wrapping_add(offset[0]),
+                position[1].wrapping_add(offset[1]),
+            ];
+            self.world_contains(neighbour_position)
+                && self.cells_input[self.cell_index(neighbour_position)]
+                    .id
+                    .element()
+                    .wet
+        })
+    }
+
+    fn cell_growth(&self, position: [u32; 2], random: u32) -> Cell {
+        let growing_neighbours = NEIGHBOUR_OFFSETS
+            .iter()
+            .filter(|offset| {
+                let neighbour_position = [
+                    position[0].wrapping_add(offset[0]),
+                    position[1].wrapping_add(offset[1]),
+                ];
+                self.world_contains(neighbour_position)
+                    && self.cells_input[self.cell_index(neighbour_position)]
+                        .id
+                        .element()
+                        .growth
+                        != 0
+            })
+            .count();
+        if growing_neighbours != 1 {
+            return Cell {
+                id: CellId::Void,
+                state: random,
+                velocity: 0,
+            };
+        }
+        let growth_positions = [
+            [position[0], position[1].wrapping_sub(1)],
+            [position[0].wrapping_sub(1), position[1].wrapping_sub(1)],
+            [position[0].wrapping_add(1), position[1].wrapping_sub(1)],
+        ];
+        for (i, growth_position) in growth_positions.into_iter().enumerate() {
+            if !self.world_contains(growth_position) {
+                continue;
+            }
+            let neighbour = self.cells_input[self.cell_index(growth_position)];
+            let element = neighbour.id.element();
+            let stage = neighbour.state & cell::STATE_MASK;
+            if element.growth == 0 || stage <= 1 {
+                continue;
+            }
+            let mut growth_chance = element::chance(element.growth_chance);
+            if i != 0 {
+                growth_chance /= 2;
+            }
+            if (hash_u32(random ^ i as u32) & 0xffff) < growth_chance {
+                return Cell {
+                    id: neighbour.id,
+                    state: (random & !cell::STATE_MASK) | (stage - 1),
+                    velocity: 0,
+                };
+            }
+        }
+        Cell {
+            id: CellId::Void,
+            state: random,
+            velocity: 0,
+        }
+    }
+
+    fn cell_charged_neighbours(&self, position: [u32; 2]) -> usize {
+        NEIGHBOUR_OFFSETS
+            .iter()
+            .filter(|offset| {
+                let neighbour_position = [
+                    position[0].wrapping_add(offset[0]),
+                    position[1].wrapping_add(offset[1]),
+                ];
+                self.world_contains(neighbour_position)
+                    && self.cells_input[self.cell_index(neighbour_position)]
+                        .id
+                        .element()
+                        .charged
+            })
+            .count()
+    }
+
+    fn cell_powered(&self, position: [u32; 2], id: CellId) -> bool {
+        if id.element().powered_into == CellId::Void {
+            return false;
+        }
+        matches!(self.cell_charged_neighbours(position), 1 | 2)
+    }
+
+    fn cell_emission(&self, position: [u32; 2], random: u32) -> Cell {
+        let emitter_position = [position[0], position[1].wrapping_add(1)];
+        if !self.world_contains(emitter_position) {
+            return Cell::spawn(CellId::Void, random);
+        }
+        let emitted_id = self.cells_input[self.cell_index(emitter_position)]
+            .id
+            .element()
+            .emits;
+        if emitted_id == CellId::Void || self.cell_charged_neighbours(emitter_position) == 0 {
+            return Cell::spawn(CellId::Void, random);
+        }
+        Cell::spawn(emitted_id, random)
+    }
+
+    fn cell_fall(&mut self, push: &Push, position: [u32; 2], cell: Cell, state: u32) -> bool {
+        let fall_down_position = [position[0], position[1].wrapping_sub(1)];
+        let fall_positions = [
+            fall_down_position,
+            [fall_down_position[0].wrapping_sub(1), fall_down_position[1]],
+            fall_down_position,
+            [fall_down_position[0].wrapping_add(1), fall_down_position[1]],
+            fall_down_position,
+        ];
+        let fall_positions_array_length = fall_positions.len() as u32;
+        let fall_index_offset = state % fall_positions_array_length;
+        for i in 0..fall_positions_array_length {
+            let fall_adjacent_position =
+                fall_positions[((i + fall_index_offset) % fall_positions_array_length) as usize];
+            if !self.world_contains(fall_adjacent_position) {
+                continue;
+            } else if self.cell_compare(push, fall_adjacent_position, cell.id) {
+                self.cell_swap(push, position, cell, fall_adjacent_position);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn cell_fall_fast(
+        &mut self,
+        push: &Push,
+        position: [u32; 2],
+        cell: Cell,
+        state: u32,
+        acceleration: u32,
+    ) -> bool {
+        let falling_cell = Cell {
+            velocity: (cell.velocity + acceleration).min(cell::MAX_VELOCITY),
+            ..cell
+        };
+        if self.cell_fall(push, position, falling_cell, state) {
+            return true;
+        }
+        let landed_cell = Cell {
+            velocity: 0,
+            ..cell
+        };
+        cell.velocity >= cell::SPLASH_VELOCITY
+            && self.cell_spread(push, position, landed_cell, state)
+    }
+
+    fn cell_rise(&mut self, push: &Push, position: [u32; 2], cell: Cell, state: u32) -> bool {
+        let rise_down_position = [position[0], position[1].wrapping_add(1)];
+        let rise_positions = [
+            rise_down_position,
+            [rise_down_position[0].wrapping_sub(1), rise_down_position[1]],
+            rise_down_position,
+            [rise_down_position[0].wrapping_add(1), rise_down_position[1]],
+            rise_down_position,
+        ];
+        let rise_positions_array_length = rise_positions.len() as u32;
+        let rise_index_offset = state % rise_positions_array_length;
+        for i in 0..rise_positions_array_length {
+            let rise_adjacent_position =
+                rise_positions[((i + rise_index_offset) % rise_positions_array_length) as usize];
+            if !self.world_contains(rise_adjacent_position) {
+                continue;
+            } else if self.cell_compare(push, rise_adjacent_position, cell.id) {
+                self.cell_swap(push, position, cell, rise_adjacent_position);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn cell_spread(&mut self, push: &Push, position: [u32; 2], cell: Cell, state: u32) -> bool {
+        let spread_positions = [
+            [position[0].wrapping_sub(1), position[1]],
+            [position[0].wrapping_add(1), position[1]],
+        ];
+        let spread_positions_array_length = spread_positions.len() as u32;
+        let spread_index_offset =
+            hash_u32(state ^ hash_vec2_u32(position)) % spread_positions_array_length;
+        for i in 0..spread_positions_array_length {
+            let spread_adjacent_position = spread_positions
+                [((i + spread_index_offset) % spread_positions_array_length) as usize];
+            if !self.world_contains(spread_adjacent_position) {
+                continue;
+            } else if self.cell_compare(push, spread_adjacent_position, cell.id) {
+                self.cell_swap(push, position, cell, spread_adjacent_position);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn compute_step(&mut self, push: &Push, global_id: [u32; 2]) {
+        let position = [
+            global_id[0] * 3 + push.local_offset[0],
+            global_id[1] * 3 + push.local_offset[1],
+        ];
+        if !self.world_contains(position) {
+            return;
+        }
+        let index = self.cell_index(position);
+        let mut cell = self.cells_input[index];
+        let id = cell.id;
+        let state = push.state;
+        if self.cells_output[index].id != CellId::Void {
+            return;
+        }
+        if push.substep != 0 {
+            if id == CellId::Void {
+                return;
+            }
+            if push.substep <= cell.velocity / cell::VELOCITY_SCALE {
+                if self.cell_fall_fast(push, position, cell, state, 0) {
+                    return;
+                }
+                cell.velocity = 0;
+            }
+            self.cells_output[index] = cell;
+            return;
+        }
+        let random = hash_u32(state ^ hash_vec2_u32(position));
+        if id == CellId::Void {
+            let mut new_cell = self.cell_growth(position, random);
+            if new_cell.id == CellId::Void {
+                new_cell = self.cell_emission(position, random);
+            }
+            if new_cell.id != CellId::Void {
+                self.cells_output[index] = new_cell;
+                self.temperatures_output[index] =
+                    element::temperature(new_cell.id.element().temperature);
+            }
+            return;
+        }
+        let temperature = self.temperatures_output[index];
+        if temperature > element::temperature(id.element().heats_above) {
+            self.cells_output[index] = Cell::spawn(id.element().heats_into, random);
+            return;
+        }
+        if temperature < element::temperature(id.element().cools_below) {
+            self.cells_output[index] = Cell::spawn(id.element().cools_into, random);
+            return;
+        }
+        let product = self.cell_reaction(push, position, id);
+        if product != id {
+            self.cells_output[index] = Cell::spawn(product, random);
+            self.temperatures_output[index] = element::temperature(product.element().temperature);
+            return;
+        }
+        if self.cell_dissolved(position, id) {
+            self.cells_output[index] = Cell {
+                id: CellId::Void,
+                state: random,
+                velocity: 0,
+            };
+            return;
+        }
+        if self.cell_germinates(position, id) {
+            self.cells_output[index] = Cell::spawn(id.element().germinates_into, random);
+            return;
+        }
+        if id.element().discharges_into != CellId::Void {
+            self.cells_output[index] = Cell::spawn(id.element().discharges_into, random);
+            return;
+        }
+        if self.cell_powered(position, id) {
+            self.cells_output[index] = Cell::spawn(id.element().powered_into, random);
+            return;
+        }
+        let lifetime = id.element().lifetime;
+        if lifetime != 0 {
+            if cell.state & cell::STATE_MASK <= 1 {
+                self.cells_output[index] = Cell::spawn(id.element().decays_into, random);
+                return;
+            }
+            cell.state -= 1;
+        } else if self.cell_ignited(position, id, random) {
+            self.cells_output[index] = Cell::spawn(CellId::Fire, random);
+            self.temperatures_output[index] =
+                temperature.max(element::temperature(CellId::Fire.element().temperature));
+            return;
+        }
+        let viscous = (random >> 16) < element::chance(id.element().viscosity);
+        let behaviour = if viscous {
+            Behaviour::Static
+        } else {
+            id.element().behaviour
+        };
+        let moved = match behaviour {
+            Behaviour::Static => false,
+            Behaviour::Powder => self.cell_fall_fast(push, position, cell, state, cell::GRAVITY),
+            Behaviour::Liquid => {
+                self.cell_fall_fast(push, position, cell, state, cell::GRAVITY)
+                    || self.cell_spread(
+                        push,
+                        position,
+                        Cell {
+                            velocity: 0,
+                            ..cell
+                        },
+                        state,
+                    )
+            }
+            Behaviour::Gas => {
+                self.cell_rise(push, position, cell, state)
+                    || self.cell_spread(push, position, cell, state)
+            }
+        };
+        if moved {
+            return;
+        }
+        if !id.element().keeps_state() {
+            cell.state = (cell.state & !cell::STATE_MASK) | (state & cell::STATE_MASK);
+        }
+        cell.velocity = 0;
+        self.cells_output[index] = cell;
+    }
+}
+
+impl Backend for CpuSimulation {
+    fn size(&self) -> [u32; 2] {
+        CpuSimulation::size(self)
+    }
+
+    fn resize(&mut self, size: [u32; 2], anchor: [Anchor; 2]) -> Result<(), SizeError> {
+        CpuSimulation::resize(self, size, anchor)
+    }
+
+    fn set_cursor(&mut self, enabled: bool, radius: u32, position: [u32; 2], brush: Brush) {
+        CpuSimulation::set_cursor(self, enabled, radius, position, brush);
+    }
+
+    fn mode(&self) -> Mode {
+        CpuSimulation::mode(self)
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        CpuSimulation::set_mode(self, mode);
+    }
+
+    fn step(&mut self) {
+        CpuSimulation::step(self);
+    }
+
+    fn state(&self) -> u32 {
+        CpuSimulation::state(self)
+    }
+
+    fn set_state(&mut self, state: u32) {
+        CpuSimulation::set_state(self, state);
+    }
+
+    fn read_cells(&self) -> Vec<Cell> {
+        self.cells_output.clone()
+    }
+
+    fn write_cells(&mut self, cells: &[Cell]) {
+        CpuSimulation::write_cells(self, cells);
+    }
+
+    fn read_temperatures(&self) -> Vec<i32> {
+        self.temperatures_output.clone()
+    }
+
+    fn write_temperatures(&mut self, temperatures: &[i32]) {
+        CpuSimulation::write_temperatures(self, temperatures);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use std::env;
+
+    fn new_simulation(size: [u32; 2], seed: u32) -> CpuSimulation {
+        CpuSimulation::new(&SimulationDescriptor {
+            size,
+            seed,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn paint(
+        backend: &mut (impl Backend + ?Sized),
+        position: [u32; 2],
+        radius: u32,
+        cell_id: CellId,
+    ) {
+        backend.set_cursor(true, radius, position, Brush::Cell(cell_id));
+        backend.set_cursor(false, radius, position, Brush::Cell(cell_id));
+    }
+
+    fn count(cells: &[Cell], cell_id: CellId) -> usize {
+        cells.iter().filter(|cell| cell.id == cell_id).count()
+    }
+
+    #[test]
+    fn sand_falls_and_settles() {
+        let mut simulation = new_simulation([16, 16], 0);
+        paint(&mut simulation, [8, 13], 2, CellId::Sand);
+        let sand = count(simulation.cells(), CellId::Sand);
+        assert!(sand > 0);
+        for _ in 0..64 {
+            simulation.step();
+        }
+        let settled = simulation.cells().to_vec();
+        for (index, cell) in settled.iter().enumerate() {
+            let [x, y] = [index as u32 % 16, index as u32 / 16];
+            if cell.id == CellId::Sand && y > 0 {
+                let below = settled[((y - 1) * 16 + x) as usize];
+                assert_ne!(below.id, CellId::Void, "sand floating at {x}, {y}");
+            }
+        }
+        simulation.step();
+        let ids = |cells: &[Cell]| cells.iter().map(|cell| cell.id).collect::<Vec<_>>();
+        assert_eq!(ids(simulation.cells()), ids(&settled));
+    }
+
+    #[test]
+    fn rejects_unsupported_sizes() {
+        for size in [[0, 8], [8, 0], [1 << 16, 1 << 16]] {
+            let descriptor = SimulationDescriptor {
+                size,
+                ..Default::default()
+            };
+            assert!(matches!(
+                CpuSimulation::new(&descriptor),
+                Err(SizeError(error_size)) if error_size == size
+            ));
+        }
+        let mut simulation = new_simulation([8, 8], 0);
+        for size in [[0, 8], [8, 0], [1 << 16, 1 << 16]] {
+            assert_eq!(
+                simulation.resize(size, [Anchor::Start; 2]),
+                Err(SizeError(size))
+            );
+            assert_eq!(simulation.size(), [8, 8]);
+        }
+        assert_eq!(simulation.resize([4, 12], [Anchor::Start; 2]), Ok(()));
+        assert_eq!(simulation.cells().len(), 4 * 12);
+    }
+
+    #[test]
+    fn movable_cells_are_conserved() {
+        let mut simulation = new_simulation([24, 24], 3);
+        paint(&mut simulation, [12, 4], 6, CellId::Rock);
+        paint(&mut simulation, [8, 18], 3, CellId::Sand);
+        paint(&mut simulation, [16, 18], 3, CellId::Oil);
+        let counts = |cells: &[Cell]| {
+            [CellId::Rock, CellId::Sand, CellId::Oil].map(|cell_id| count(cells, cell_id))
+        };
+        let before = counts(simulation.cells());
+        for _ in 0..100 {
+            simulation.step();
+            assert_eq!(counts(simulation.cells()), before);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_grid() {
+        let run = |seed| {
+            let mut simulation = new_simulation([32, 32], seed);
+            paint(&mut simulation, [10, 28], 3, CellId::Sand);
+            paint(&mut simulation, [22, 28], 3, CellId::Water);
+            paint(&mut simulation, [16, 4], 3, CellId::Lava);
+            for _ in 0..100 {
+                simulation.step();
+            }
+            (simulation.read_cells(), simulation.read_temperatures())
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).0, run(8).0);
+    }
+
+    #[test]
+    fn sand_soaks_up_water() {
+        let mut simulation = new_simulation([20, 12], 5);
+        for x in 0..20 {
+            for y in 0..4 {
+                paint(&mut simulation, [x, y], 1, CellId::Sand);
+            }
+        }
+        paint(&mut simulation, [10, 6], 1, CellId::Water);
+        for _ in 0..300 {
+            simulation.step();
+        }
+        assert_eq!(count(simulation.cells(), CellId::Water), 0);
+        assert_eq!(count(simulation.cells(), CellId::WetSand), 1);
+        assert_eq!(count(simulation.cells(), CellId::Sand), 79);
+
+        let mut simulation = new_simulation([10, 12], 6);
+        for x in 0..5 {
+            for y in 0..5 {
+                paint(&mut simulation, [x, y], 1, CellId::Sand);
+                paint(&mut simulation, [x + 5, y], 1, CellId::Water);
+            }
+        }
+        for _ in 0..300 {
+            simulation.step();
+        }
+        let water = count(simulation.cells(), CellId::Water);
+        let wet_sand = count(simulation.cells(), CellId::WetSand);
+        assert!(wet_sand > 0);
+        assert_eq!(water + wet_sand, 25);
+        assert_eq!(count(simulation.cells(), CellId::Sand) + wet_sand, 25);
+    }
+
+    #[test]
+    fn electron_travels_along_wire() {
+        let mut simulation = new_simulation([12, 4], 0);
+        for x in 1..11 {
+            paint(&mut simulation, [x, 2], 1, CellId::Copper);
+        }
+        paint(&mut simulation, [1, 2], 1, CellId::ElectronTail);
+        paint(&mut simulation, [2, 2], 1, CellId::ElectronHead);
+        for step in 0..6 {
+            let wire = &simulation.cells()[2 * 12..3 * 12];
+            let head = wire.iter().position(|cell| cell.id == CellId::ElectronHead);
+            assert_eq!(head, Some(2 + step));
+            assert_eq!(wire[1 + step].id, CellId::ElectronTail);
+            assert_eq!(count(wire, CellId::ElectronHead), 1);
+            simulation.step();
+        }
+    }
+
+    #[test]
+    fn powered_spout_emits() {
+        let mut simulation = new_simulation([8, 8], 0);
+        paint(&mut simulation, [4, 5], 1, CellId::Spout);
+        simulation.step();
+        assert_eq!(count(simulation.cells(), CellId::Water), 0);
+        paint(&mut simulation, [3, 5], 1, CellId::ElectronHead);
+        simulation.step();
+        assert_eq!(simulation.cells()[4 * 8 + 4].id, CellId::Water);
+    }
+
+    fn live_cells(simulation: &CpuSimulation) -> Vec<[u32; 2]> {
+        let width = simulation.size()[0];
+        (0..simulation.cells().len() as u32)
+            .filter(|&index| simulation.cells()[index as usize].id == CellId::Life)
+            .map(|index| [index % width, index / width])
+            .collect()
+    }
+
+    #[test]
+    fn life_blinker_oscillates() {
+        let mut simulation = CpuSimulation::new(&SimulationDescriptor {
+            size: [10, 10],
+            mode: Mode::Life(Rule::CONWAY),
+            ..Default::default()
+        })
+        .unwrap();
+        let vertical = [[5, 4], [5, 5], [5, 6]];
+        for position in vertical {
+            paint(&mut simulation, position, 1, CellId::Life);
+        }
+        paint(&mut simulation, [1, 8], 1, CellId::Sand);
+        simulation.step();
+        assert_eq!(live_cells(&simulation), [[4, 5], [5, 5], [6, 5]]);
+        simulation.step();
+        assert_eq!(live_cells(&simulation), vertical);
+        assert_eq!(simulation.cells()[8 * 10 + 1].id, CellId::Sand);
+    }
+
+    #[test]
+    fn matches_gpu_simulation() {
+        let descriptor = SimulationDescriptor {
+            size: [48, 40],
+            seed: 11,
+            mode: Mode::SandAndLife(Rule::CONWAY),
+            ..Default::default()
+        };
+        let mut gpu = match Simulation::new_headless(&descriptor) {
+            Ok(simulation) => simulation,
+            Err(err) if env::var_os("CASIM_SKIP_GPU_TESTS").is_some() => {
+                eprintln!("skipping GPU parity test: {err}");
+                return;
+            }
+            Err(err) => panic!("{err}, set CASIM_SKIP_GPU_TESTS to skip the GPU parity test"),
+        };
+        let mut cpu = CpuSimulation::new(&descriptor).unwrap();
+        for backend in [&mut cpu as &mut dyn Backend, &mut gpu] {
+            for (index, element) in element::ELEMENTS.iter().enumerate().skip(1) {
+                let index = index as u32;
+                let position = [3 + index % 7 * 6, 5 + index / 7 * 11];
+                paint(backend, position, 3, element.id);
+            }
+            let heat = Brush::Temperature(2000.0);
+            backend.set_cursor(true, 4, [20, 20], heat);
+            backend.set_cursor(false, 4, [20, 20], heat);
+        }
+        for step in 0..100 {
+            cpu.step();
+            gpu.step();
+            assert_eq!(
+                cpu.read_cells(),
+                gpu.read_cells(),
+                "cells differ after step {step}"
+            );
+            assert_eq!(
+                cpu.read_temperatures(),
+                gpu.read_temperatures(),
+                "temperatures differ after step {step}"
+            );
+        }
+    }
+}
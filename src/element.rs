@@ -0,0 +1,536 @@
+use crate::cell;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Behaviour {
+    Static,
+    Powder,
+    Liquid,
+    Gas,
+}
+
+impl Behaviour {
+    const ALL: [Self; 4] = [Self::Static, Self::Powder, Self::Liquid, Self::Gas];
+
+    pub fn movable(self) -> bool {
+        self != Self::Static
+    }
+
+    fn shader_name(self) -> &'static str {
+        match self {
+            Self::Static => "BEHAVIOUR_STATIC",
+            Self::Powder => "BEHAVIOUR_POWDER",
+            Self::Liquid => "BEHAVIOUR_LIQUID",
+            Self::Gas => "BEHAVIOUR_GAS",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Element {
+    /// Indexes `ELEMENTS` and the generated shader tables, so ids must stay contiguous.
+    pub id: CellId,
+    pub name: &'static str,
+    /// Each cell is drawn in this colour darkened by up to `color_variation`, depending on its
+    /// shade.
+    pub color: [f32; 3],
+    pub color_variation: f32,
+    /// Only used by movable (non-static) elements: a mover swaps with a lighter mover or void in
+    /// the direction it moves, so heavier elements sink and lighter ones float.
+    pub density: u32,
+    pub behaviour: Behaviour,
+    pub lifetime: u32,
+    pub decays_into: CellId,
+    pub flammability: f32,
+    pub ignites: bool,
+    /// The spawn temperature. Temperatures are in degrees Celsius, and void always stays at the
+    /// ambient temperature and slowly pulls its neighbours towards it.
+    pub temperature: f32,
+    pub conductivity: f32,
+    pub heats_above: f32,
+    pub heats_into: CellId,
+    pub cools_below: f32,
+    pub cools_into: CellId,
+    /// The chance that a mover stays put for a pass.
+    pub viscosity: f32,
+    /// Every step pairs each cell with one of its neighbours, and a cell paired with a
+    /// `reacts_with` neighbour turns into `reacts_into` while the neighbour turns into
+    /// `reactant_into`, both at their spawn temperatures. Void means the element does not react.
+    pub reacts_with: CellId,
+    pub reacts_into: CellId,
+    pub reactant_into: CellId,
+    /// A corrosive cell dissolves a neighbour with a chance of `1 - acid_resistance` per step and
+    /// is used up doing so.
+    pub corrosive: bool,
+    pub acid_resistance: f32,
+    pub wet: bool,
+    /// Turns the cell into this element when it touches a `wet` cell.
+    pub germinates_into: CellId,
+    /// Growing cells start at this growth stage and spread into void above them with
+    /// `growth_chance` per step, and diagonally upwards with half of it, each new cell one stage
+    /// lower than its parent.
+    pub growth: u32,
+    pub growth_chance: f32,
+    pub charged: bool,
+    /// Electricity follows Wireworld: the cell turns into this element next to one or two
+    /// `charged` cells.
+    pub powered_into: CellId,
+    /// The cell always turns into this element on the next step.
+    pub discharges_into: CellId,
+    /// Dropped into the void below the cell while it touches a `charged` cell.
+    pub emits: CellId,
+}
+
+impl Element {
+    const DEFAULT: Self = Self {
+        id: CellId::Void,
+        name: "",
+        color: [0.0, 0.0, 0.0],
+        color_variation: 0.0,
+        density: 0,
+        behaviour: Behaviour::Static,
+        lifetime: 0,
+        decays_into: CellId::Void,
+        flammability: 0.0,
+        ignites: false,
+        temperature: AMBIENT_TEMPERATURE,
+        conductivity: 0.0,
+        heats_above: f32::INFINITY,
+        heats_into: CellId::Void,
+        cools_below: f32::NEG_INFINITY,
+        cools_into: CellId::Void,
+        viscosity: 0.0,
+        reacts_with: CellId::Void,
+        reacts_into: CellId::Void,
+        reactant_into: CellId::Void,
+        corrosive: false,
+        acid_resistance: 1.0,
+        wet: false,
+        germinates_into: CellId::Void,
+        growth: 0,
+        growth_chance: 0.0,
+        charged: false,
+        powered_into: CellId::Void,
+        discharges_into: CellId::Void,
+        emits: CellId::Void,
+    };
+
+    // Cells with a lifetime or a growth stage store it in the low bits of `Cell.state`, so it must
+    // not be replaced by random values.
+    pub fn keeps_state(&self) -> bool {
+        self.lifetime != 0 || self.growth != 0
+    }
+}
+
+pub const AMBIENT_TEMPERATURE: f32 = 20.0;
+pub const MIN_TEMPERATURE: f32 = -273.0;
+pub const MAX_TEMPERATURE: f32 = 5000.0;
+const TEMPERATURE_SCALE: f32 = 256.0;
+
+macro_rules! elements {
+    ($($variant:ident = $id:literal { $($field:ident: $value:expr,)* },)*) => {
+        #[repr(u32)]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        pub enum CellId {
+            #[default]
+            $($variant = $id,)*
+        }
+
+        impl TryFrom<u32> for CellId {
+            type Error = u32;
+
+            fn try_from(value: u32) -> Result<Self, Self::Error> {
+                match value {
+                    $($id => Ok(Self::$variant),)*
+                    _ => Err(value),
+                }
+            }
+        }
+
+        pub const ELEMENTS: &[Element] = &[$(Element {
+            id: CellId::$variant,
+            $($field: $value,)*
+            ..Element::DEFAULT
+        },)*];
+
+        // Fails the build when an element is listed out of id order or an id is skipped.
+        const _: () = {
+            let mut index = 0;
+            while index < ELEMENTS.len() {
+                assert!(
+                    ELEMENTS[index].id as usize == index,
+                    "element ids must be contiguous and listed in order"
+                );
+                index += 1;
+            }
+        };
+    };
+}
+
+elements! {
+    Void = 0x00 {
+        name: "void",
+        conductivity: 0.02,
+    },
+    Rock = 0x01 {
+        name: "rock",
+        color: [0.4, 0.4, 0.4],
+        color_variation: 0.25,
+        conductivity: 0.3,
+        heats_above: 1200.0,
+        heats_into: CellId::Lava,
+        acid_resistance: 0.98,
+    },
+    Sand = 0x02 {
+        name: "sand",
+        color: [0.91, 0.773, 0.498],
+        color_variation: 0.15,
+        density: 16,
+        behaviour: Behaviour::Powder,
+        conductivity: 0.2,
+        heats_above: 1500.0,
+        heats_into: CellId::Glass,
+        reacts_with: CellId::Water,
+        reacts_into: CellId::WetSand,
+        acid_resistance: 0.8,
+    },
+    Water = 0x03 {
+        name: "water",
+        color: [0.0, 0.0, 1.0],
+        color_variation: 0.08,
+        density: 10,
+        behaviour: Behaviour::Liquid,
+        conductivity: 0.5,
+        heats_above: 100.0,
+        heats_into: CellId::Steam,
+        cools_below: -1.0,
+        cools_into: CellId::Ice,
+        wet: true,
+    },
+    Wood = 0x04 {
+        name: "wood",
+        color: [0.2, 0.08, 0.02],
+        color_variation: 0.3,
+        flammability: 0.05,
+        conductivity: 0.1,
+        acid_resistance: 0.9,
+    },
+    Fire = 0x05 {
+        name: "fire",
+        color: [1.0, 0.3, 0.0],
+        color_variation: 0.3,
+        lifetime: 60,
+        decays_into: CellId::Smoke,
+        ignites: true,
+        temperature: 600.0,
+        conductivity: 0.2,
+    },
+    Smoke = 0x06 {
+        name: "smoke",
+        color: [0.25, 0.25, 0.25],
+        color_variation: 0.2,
+        density: 1,
+        behaviour: Behaviour::Gas,
+        lifetime: 120,
+        conductivity: 0.05,
+    },
+    Steam = 0x07 {
+        name: "steam",
+        color: [0.8, 0.8, 0.85],
+        color_variation: 0.1,
+        density: 1,
+        behaviour: Behaviour::Gas,
+        temperature: 150.0,
+        conductivity: 0.1,
+        cools_below: 90.0,
+        cools_into: CellId::Water,
+    },
+    Ice = 0x08 {
+        name: "ice",
+        color: [0.6, 0.8, 1.0],
+        color_variation: 0.1,
+        temperature: -20.0,
+        conductivity: 0.6,
+        heats_above: 1.0,
+        heats_into: CellId::Water,
+        acid_resistance: 0.95,
+    },
+    Glass = 0x09 {
+        name: "glass",
+        color: [0.7, 0.85, 0.9],
+        color_variation: 0.05,
+        conductivity: 0.2,
+    },
+    Lava = 0x0a {
+        name: "lava",
+        color: [1.0, 0.25, 0.0],
+        color_variation: 0.2,
+        density: 27,
+        behaviour: Behaviour::Liquid,
+        ignites: true,
+        temperature: 1400.0,
+        conductivity: 0.3,
+        cools_below: 700.0,
+        cools_into: CellId::Rock,
+        viscosity: 0.6,
+        reacts_with: CellId::Water,
+        reacts_into: CellId::Rock,
+        reactant_into: CellId::Steam,
+    },
+    Oil = 0x0b {
+        name: "oil",
+        color: [0.35, 0.25, 0.05],
+        color_variation: 0.15,
+        density: 9,
+        behaviour: Behaviour::Liquid,
+        flammability: 0.3,
+        conductivity: 0.15,
+    },
+    Acid = 0x0c {
+        name: "acid",
+        color: [0.4, 1.0, 0.2],
+        color_variation: 0.1,
+        density: 11,
+        behaviour: Behaviour::Liquid,
+        conductivity: 0.4,
+        corrosive: true,
+    },
+    WetSand = 0x0d {
+        name: "wet sand",
+        color: [0.55, 0.45, 0.28],
+        color_variation: 0.15,
+        density: 18,
+        behaviour: Behaviour::Powder,
+        conductivity: 0.3,
+        heats_above: 100.0,
+        heats_into: CellId::Sand,
+        acid_resistance: 0.8,
+        wet: true,
+    },
+    Seed = 0x0e {
+        name: "seed",
+        color: [0.45, 0.3, 0.1],
+        color_variation: 0.25,
+        density: 14,
+        behaviour: Behaviour::Powder,
+        flammability: 0.1,
+        conductivity: 0.1,
+        acid_resistance: 0.7,
+        germinates_into: CellId::Plant,
+    },
+    Plant = 0x0f {
+        name: "plant",
+        color: [0.1, 0.6, 0.1],
+        color_variation: 0.3,
+        flammability: 0.2,
+        conductivity: 0.1,
+        acid_resistance: 0.7,
+        growth: 24,
+        growth_chance: 0.1,
+    },
+    Copper = 0x10 {
+        name: "copper",
+        color: [0.72, 0.45, 0.2],
+        color_variation: 0.15,
+        conductivity: 0.9,
+        heats_above: 1085.0,
+        heats_into: CellId::Lava,
+        acid_resistance: 0.9,
+        powered_into: CellId::ElectronHead,
+    },
+    ElectronHead = 0x11 {
+        name: "electron head",
+        color: [0.3, 0.6, 1.0],
+        ignites: true,
+        conductivity: 0.9,
+        acid_resistance: 0.9,
+        charged: true,
+        discharges_into: CellId::ElectronTail,
+    },
+    ElectronTail = 0x12 {
+        name: "electron tail",
+        color: [1.0, 0.35, 0.2],
+        conductivity: 0.9,
+        acid_resistance: 0.9,
+        discharges_into: CellId::Copper,
+    },
+    Spout = 0x13 {
+        name: "spout",
+        color: [0.3, 0.3, 0.5],
+        color_variation: 0.1,
+        conductivity: 0.3,
+        acid_resistance: 0.95,
+        emits: CellId::Water,
+    },
+    // Life cells stay put under the falling-sand rules and only evolve in a Life-like mode.
+    Life = 0x14 {
+        name: "life",
+        color: [0.95, 0.95, 0.8],
+        conductivity: 0.1,
+        acid_resistance: 0.5,
+    },
+}
+
+impl CellId {
+    pub fn element(self) -> &'static Element {
+        &ELEMENTS[self as usize]
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static Element> {
+    ELEMENTS.iter().find(|element| element.name == name)
+}
+
+pub fn chance(probability: f32) -> u32 {
+    (probability.clamp(0.0, 1.0) * 65536.0) as u32
+}
+
+// Temperatures are fixed point so every backend diffuses heat bit for bit identically.
+pub fn temperature(celsius: f32) -> i32 {
+    (celsius.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE) * TEMPERATURE_SCALE).round() as i32
+}
+
+pub fn celsius(temperature: i32) -> f32 {
+    temperature as f32 / TEMPERATURE_SCALE
+}
+
+pub fn conductivity(value: f32) -> i32 {
+    (value.clamp(0.0, 1.0) * TEMPERATURE_SCALE) as i32
+}
+
+fn write_table<T>(source: &mut String, name: &str, ty: &str, value: impl Fn(&Element) -> T)
+where
+    T: std::fmt::Display,
+{
+    let count = ELEMENTS.len();
+    let _ = writeln!(
+        source,
+        "var<private> {name}: array<{ty}, {count}> = array<{ty}, {count}>("
+    );
+    for element in ELEMENTS {
+        let _ = writeln!(source, "    {},", value(element));
+    }
+    let _ = writeln!(source, ");");
+}
+
+pub fn shader_definitions() -> String {
+    let mut source = String::new();
+    for element in ELEMENTS {
+        let _ = writeln!(
+            source,
+            "const CELL_ID_{}: u32 = {:#04x}u;",
+            element.name.to_uppercase().replace(' ', "_"),
+            element.id as u32,
+        );
+    }
+    let _ = writeln!(source, "const CELL_ID_COUNT: u32 = {}u;", ELEMENTS.len());
+    let _ = writeln!(
+        source,
+        "const TEMPERATURE_SCALE: f32 = {TEMPERATURE_SCALE:?};"
+    );
+    for (name, value) in [
+        ("VELOCITY_SCALE", cell::VELOCITY_SCALE),
+        ("GRAVITY", cell::GRAVITY),
+        ("MAX_VELOCITY", cell::MAX_VELOCITY),
+        ("SPLASH_VELOCITY", cell::SPLASH_VELOCITY),
+        ("SHADE_SHIFT", cell::SHADE_SHIFT),
+        ("STATE_MASK", cell::STATE_MASK),
+    ] {
+        let _ = writeln!(source, "const {name}: u32 = {value}u;");
+    }
+    for behaviour in Behaviour::ALL {
+        let _ = writeln!(
+            source,
+            "const {}: u32 = {}u;",
+            behaviour.shader_name(),
+            behaviour as u32,
+        );
+    }
+    write_table(&mut source, "cell_colors", "vec3<f32>", |element| {
+        let [r, g, b] = element.color;
+        format!("vec3({r:?}, {g:?}, {b:?})")
+    });
+    write_table(&mut source, "cell_color_variations", "f32", |element| {
+        format!("{:?}", element.color_variation)
+    });
+    write_table(&mut source, "cell_densities", "u32", |element| {
+        format!("{}u", element.density)
+    });
+    write_table(&mut source, "cell_behaviours", "u32", |element| {
+        element.behaviour.shader_name()
+    });
+    write_table(&mut source, "cell_lifetimes", "u32", |element| {
+        format!("{}u", element.lifetime)
+    });
+    write_table(&mut source, "cell_decays", "u32", |element| {
+        format!("{}u", element.decays_into as u32)
+    });
+    write_table(&mut source, "cell_flammabilities", "u32", |element| {
+        format!("{}u", chance(element.flammability))
+    });
+    write_table(&mut source, "cell_igniters", "bool", |element| {
+        element.ignites
+    });
+    write_table(&mut source, "cell_temperatures", "i32", |element| {
+        format!("{}i", temperature(element.temperature))
+    });
+    write_table(&mut source, "cell_conductivities", "i32", |element| {
+        format!("{}i", conductivity(element.conductivity))
+    });
+    write_table(&mut source, "cell_heats_above", "i32", |element| {
+        format!("{}i", temperature(element.heats_above))
+    });
+    write_table(&mut source, "cell_heats_into", "u32", |element| {
+        format!("{}u", element.heats_into as u32)
+    });
+    write_table(&mut source, "cell_cools_below", "i32", |element| {
+        format!("{}i", temperature(element.cools_below))
+    });
+    write_table(&mut source, "cell_cools_into", "u32", |element| {
+        format!("{}u", element.cools_into as u32)
+    });
+    write_table(&mut source, "cell_viscosities", "u32", |element| {
+        format!("{}u", chance(element.viscosity))
+    });
+    write_table(&mut source, "cell_reacts_with", "u32", |element| {
+        format!("{}u", element.reacts_with as u32)
+    });
+    write_table(&mut source, "cell_reacts_into", "u32", |element| {
+        format!("{}u", element.reacts_into as u32)
+    });
+    write_table(&mut source, "cell_reactant_into", "u32", |element| {
+        format!("{}u", element.reactant_into as u32)
+    });
+    write_table(&mut source, "cell_corrosives", "bool", |element| {
+        element.corrosive
+    });
+    write_table(&mut source, "cell_dissolve_chances", "u32", |element| {
+        format!("{}u", chance(1.0 - element.acid_resistance))
+    });
+    write_table(&mut source, "cell_keeps_state", "bool", |element| {
+        element.keeps_state()
+    });
+    write_table(&mut source, "cell_wet", "bool", |element| element.wet);
+    write_table(&mut source, "cell_germinates_into", "u32", |element| {
+        format!("{}u", element.germinates_into as u32)
+    });
+    write_table(&mut source, "cell_growths", "u32", |element| {
+        format!("{}u", element.growth)
+    });
+    write_table(&mut source, "cell_growth_chances", "u32", |element| {
+        format!("{}u", chance(element.growth_chance))
+    });
+    write_table(&mut source, "cell_charged", "bool", |element| {
+        element.charged
+    });
+    write_table(&mut source, "cell_powered_into", "u32", |element| {
+        format!("{}u", element.powered_into as u32)
+    });
+    write_table(&mut source, "cell_discharges_into", "u32", |element| {
+        format!("{}u", element.discharges_into as u32)
+    });
+    write_table(&mut source, "cell_emits", "u32", |element| {
+        format!("{}u", element.emits as u32)
+    });
+    source
+}
@@ -0,0 +1,277 @@
+use crate::{backend::Backend, cell::Cell};
+use std::{collections::VecDeque, mem};
+
+struct Stroke {
+    world_size: [u32; 2],
+    cells: Vec<Cell>,
+    temperatures: Vec<i32>,
+    /// Whether the brush covered each cell of the world at some point of the stroke.
+    painted: Vec<bool>,
+}
+
+/// The cells and temperatures of the cells an edit covers, in the order of `Edit.indices`.
+#[derive(PartialEq)]
+struct Region {
+    cells: Vec<Cell>,
+    temperatures: Vec<i32>,
+}
+
+// Only the cells under the brush are stored, so undoing a stroke leaves the cells around it
+// alone, even though the simulation keeps moving them while the stroke is drawn.
+struct Edit {
+    world_size: [u32; 2],
+    indices: Vec<u32>,
+    before: Region,
+    after: Region,
+}
+
+impl Edit {
+    fn byte_size(&self) -> usize {
+        self.indices.len() * mem::size_of::<u32>()
+            + [&self.before, &self.after]
+                .iter()
+                .map(|region| {
+                    region.cells.len() * mem::size_of::<Cell>()
+                        + region.temperatures.len() * mem::size_of::<i32>()
+                })
+                .sum::<usize>()
+    }
+
+    fn gather<T: Copy>(values: &[T], indices: &[u32]) -> Vec<T> {
+        indices
+            .iter()
+            .map(|&index| values[index as usize])
+            .collect()
+    }
+
+    fn scatter<T: Copy>(&self, values: &mut [T], region: &[T]) {
+        for (&index, &value) in self.indices.iter().zip(region) {
+            values[index as usize] = value;
+        }
+    }
+
+    fn apply(&self, backend: &mut impl Backend, region: &Region) -> bool {
+        if backend.size() != self.world_size {
+            return false;
+        }
+        let mut cells = backend.read_cells();
+        self.scatter(&mut cells, &region.cells);
+        backend.write_cells(&cells);
+        let mut temperatures = backend.read_temperatures();
+        self.scatter(&mut temperatures, &region.temperatures);
+        backend.write_temperatures(&temperatures);
+        true
+    }
+}
+
+pub struct History {
+    budget: usize,
+    used: usize,
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    stroke: Option<Stroke>,
+}
+
+impl History {
+    pub const DEFAULT_BUDGET: usize = 64 << 20;
+
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            stroke: None,
+        }
+    }
+
+    pub fn begin_stroke(&mut self, backend: &impl Backend) {
+        let cells = backend.read_cells();
+        self.stroke = Some(Stroke {
+            world_size: backend.size(),
+            painted: vec![false; cells.len()],
+            cells,
+            temperatures: backend.read_temperatures(),
+        });
+    }
+
+    /// Marks the cells the brush covers at `position`, the same disc `Backend::set_cursor` paints.
+    pub fn extend_stroke(&mut self, position: [u32; 2], radius: u32) {
+        let Some(stroke) = &mut self.stroke else {
+            return;
+        };
+        let [width, height] = stroke.world_size;
+        let min = position.map(|value| value.saturating_sub(radius));
+        let max = [
+            position[0].saturating_add(radius).min(width - 1),
+            position[1].saturating_add(radius).min(height - 1),
+        ];
+        let squared_radius = radius as u64 * radius as u64;
+        for y in min[1]..=max[1] {
+            for x in min[0]..=max[0] {
+                let displacement = [x.abs_diff(position[0]), y.abs_diff(position[1])];
+                let squared_distance = displacement[0] as u64 * displacement[0] as u64
+                    + displacement[1] as u64 * displacement[1] as u64;
+                if squared_distance < squared_radius {
+                    stroke.painted[(y * width + x) as usize] = true;
+                }
+            }
+        }
+    }
+
+    pub fn end_stroke(&mut self, backend: &impl Backend) {
+        let Some(stroke) = self.stroke.take() else {
+            return;
+        };
+        if backend.size() != stroke.world_size {
+            return;
+        }
+        let indices: Vec<u32> = (0..stroke.painted.len() as u32)
+            .filter(|&index| stroke.painted[index as usize])
+            .collect();
+        let edit = Edit {
+            world_size: stroke.world_size,
+            before: Region {
+                cells: Edit::gather(&stroke.cells, &indices),
+                temperatures: Edit::gather(&stroke.temperatures, &indices),
+            },
+            after: Region {
+                cells: Edit::gather(&backend.read_cells(), &indices),
+                temperatures: Edit::gather(&backend.read_temperatures(), &indices),
+            },
+            indices,
+        };
+        if edit.before == edit.after {
+            return;
+        }
+        self.redo.clear();
+        self.used += edit.byte_size();
+        self.undo.push_back(edit);
+        while self.used > self.budget {
+            let Some(edit) = self.undo.pop_front() else {
+                break;
+            };
+            self.used -= edit.byte_size();
+        }
+    }
+
+    pub fn undo(&mut self, backend: &mut impl Backend) -> bool {
+        let Some(edit) = self.undo.pop_back() else {
+            return false;
+        };
+        self.used -= edit.byte_size();
+        if !edit.apply(backend, &edit.before) {
+            self.clear();
+            return false;
+        }
+        self.redo.push(edit);
+        true
+    }
+
+    pub fn redo(&mut self, backend: &mut impl Backend) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        if !edit.apply(backend, &edit.after) {
+            self.clear();
+            return false;
+        }
+        self.used += edit.byte_size();
+        self.undo.push_back(edit);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.used = 0;
+        self.undo.clear();
+        self.redo.clear();
+        self.stroke = None;
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{Brush, SimulationDescriptor},
+        cpu::CpuSimulation,
+        element::CellId,
+    };
+
+    fn new_simulation() -> CpuSimulation {
+        CpuSimulation::new(&SimulationDescriptor {
+            size: [8, 8],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn paint(simulation: &mut CpuSimulation, position: [u32; 2], radius: u32) {
+        simulation.set_cursor(true, radius, position, Brush::Cell(CellId::Rock));
+        simulation.set_cursor(false, radius, position, Brush::Cell(CellId::Rock));
+    }
+
+    fn stroke(history: &mut History, simulation: &mut CpuSimulation, position: [u32; 2]) {
+        history.begin_stroke(simulation);
+        history.extend_stroke(position, 2);
+        paint(simulation, position, 2);
+        history.end_stroke(simulation);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_cells() {
+        let mut simulation = new_simulation();
+        let mut history = History::default();
+        let empty = simulation.read_cells();
+        stroke(&mut history, &mut simulation, [3, 3]);
+        let painted = simulation.read_cells();
+        assert_ne!(painted, empty);
+        assert!(history.undo(&mut simulation));
+        assert_eq!(simulation.read_cells(), empty);
+        assert!(history.redo(&mut simulation));
+        assert_eq!(simulation.read_cells(), painted);
+        assert!(!history.redo(&mut simulation));
+    }
+
+    #[test]
+    fn evicts_oldest_edits_over_budget() {
+        let mut simulation = new_simulation();
+        // Each stroke covers a disc of 9 cells, stored before and after.
+        let edit_size =
+            9 * (mem::size_of::<u32>() + 2 * (mem::size_of::<Cell>() + mem::size_of::<i32>()));
+        let mut history = History::new(2 * edit_size);
+        for x in [1, 4, 6] {
+            stroke(&mut history, &mut simulation, [x, 4]);
+        }
+        assert!(history.undo(&mut simulation));
+        assert!(history.undo(&mut simulation));
+        assert!(!history.undo(&mut simulation));
+        assert_eq!(simulation.read_cells()[4 * 8 + 1].id, CellId::Rock);
+        assert_eq!(simulation.read_cells()[4 * 8 + 4].id, CellId::Void);
+    }
+
+    #[test]
+    fn undo_only_restores_painted_cells() {
+        let mut simulation = new_simulation();
+        let mut history = History::default();
+        history.begin_stroke(&simulation);
+        for position in [[1, 1], [6, 6]] {
+            history.extend_stroke(position, 1);
+            paint(&mut simulation, position, 1);
+        }
+        // Inside the rectangle the stroke spans, but never under the brush.
+        paint(&mut simulation, [6, 1], 1);
+        history.end_stroke(&simulation);
+        assert!(history.undo(&mut simulation));
+        let cells = simulation.read_cells();
+        assert_eq!(cells[8 + 1].id, CellId::Void);
+        assert_eq!(cells[6 * 8 + 6].id, CellId::Void);
+        assert_eq!(cells[8 + 6].id, CellId::Rock);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    backend::{self, Anchor, Backend, SizeError},
+    cell::{hash_vec2_u32, Cell},
+    element::{self, CellId},
+};
+use std::{
+    error, fmt,
+    io::{Read, Write},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct ImportOptions {
+    pub tolerance: f32,
+    pub unknown_as_void: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.1,
+            unknown_as_void: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Decoding(png::DecodingError),
+    UnsupportedColorType(png::ColorType),
+    InvalidSize([u32; 2]),
+    UnknownColor { position: [u32; 2], color: [u8; 3] },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decoding(err) => write!(f, "{err}"),
+            Self::UnsupportedColorType(color_type) => {
+                write!(f, "unsupported color type {color_type:?}")
+            }
+            Self::InvalidSize([width, height]) => {
+                write!(f, "image size {width}x{height} is too large for a world")
+            }
+            Self::UnknownColor {
+                position: [x, y],
+                color: [r, g, b],
+            } => write!(
+                f,
+                "color #{r:02x}{g:02x}{b:02x} at ({x}, {y}) does not match any element"
+            ),
+        }
+    }
+}
+
+impl error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Decoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<png::DecodingError> for ImportError {
+    fn from(err: png::DecodingError) -> Self {
+        Self::Decoding(err)
+    }
+}
+
+impl From<SizeError> for ImportError {
+    fn from(SizeError(size): SizeError) -> Self {
+        Self::InvalidSize(size)
+    }
+}
+
+// Element colors are linear, so they are encoded the same way the sRGB render target encodes them.
+pub fn element_color(id: CellId) -> [u8; 3] {
+    id.element().color.map(|channel| {
+        let channel = channel.clamp(0.0, 1.0);
+        let encoded = if channel <= 0.0031308 {
+            channel * 12.92
+        } else {
+            1.055 * channel.powf(1.0 / 2.4) - 0.055
+        };
+        (encoded * 255.0).round() as u8
+    })
+}
+
+pub fn export(backend: &impl Backend, writer: impl Write) -> Result<(), png::EncodingError> {
+    let size = backend.size();
+    let cells = backend.read_cells();
+    let mut data = Vec::with_capacity(cells.len() * 3);
+    for row in cells.chunks_exact(size[0] as usize).rev() {
+        for cell in row {
+            data.extend_from_slice(&element_color(cell.id));
+        }
+    }
+    let mut encoder = png::Encoder::new(writer, size[0], size[1]);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()
+}
+
+/// Writes RGBA pixels that are already sRGB encoded, such as a rendered frame, row by row from the
+/// top.
+pub fn write_rgba(
+    writer: impl Write,
+    size: [u32; 2],
+    pixels: &[u8],
+) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, size[0], size[1]);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()
+}
+
+pub fn import(
+    backend: &mut impl Backend,
+    reader: impl Read,
+    options: &ImportOptions,
+) -> Result<(), ImportError> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let size = [reader.info().width, reader.info().height];
+    backend::check_size(size)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        color_type => return Err(ImportError::UnsupportedColorType(color_type)),
+    };
+    let mut cells = vec![Cell::default(); (size[0] * size[1]) as usize];
+    for (row_index, row) in data
+        .chunks_exact(info.line_size)
+        .take(size[1] as usize)
+        .enumerate()
+    {
+        let y = size[1] - 1 - row_index as u32;
+        for (x, pixel) in row
+            .chunks_exact(channels)
+            .take(size[0] as usize)
+            .enumerate()
+        {
+            let position = [x as u32, y];
+            let (color, alpha) = match *pixel {
+                [l] => ([l, l, l], u8::MAX),
+                [l, a] => ([l, l, l], a),
+                [r, g, b] => ([r, g, b], u8::MAX),
+                [r, g, b, a] => ([r, g, b], a),
+                _ => unreachable!(),
+            };
+            let id = if alpha == 0 {
+                CellId::Void
+            } else if let Some(id) = nearest_element(color, options.tolerance) {
+                id
+            } else if options.unknown_as_void {
+                CellId::Void
+            } else {
+                return Err(ImportError::UnknownColor { position, color });
+            };
+            cells[(y * size[0]) as usize + x] = Cell::spawn(id, hash_vec2_u32(position));
+        }
+    }
+    if backend.size() != size {
+        backend.resize(size, [Anchor::Start; 2])?;
+    }
+    let temperatures: Vec<i32> = cells
+        .iter()
+        .map(|cell| element::temperature(cell.id.element().temperature))
+        .collect();
+    backend.write_cells(&cells);
+    backend.write_temperatures(&temperatures);
+    Ok(())
+}
+
+fn nearest_element(color: [u8; 3], tolerance: f32) -> Option<CellId> {
+    element::ELEMENTS
+        .iter()
+        .map(|element| {
+            let element_color = element_color(element.id);
+            let squared_distance: f32 = (0..3)
+                .map(|channel| {
+                    let difference =
+                        (color[channel] as f32 - element_color[channel] as f32) / 255.0;
+                    difference * difference
+                })
+                .sum();
+            (element.id, squared_distance.sqrt())
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .filter(|&(_, distance)| distance <= tolerance)
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::SimulationDescriptor, cpu::CpuSimulation};
+
+    #[test]
+    fn rejects_oversized_images() {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 4096, 4096);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&vec![0; 4096 * 4096]).unwrap();
+        writer.finish().unwrap();
+        let mut simulation = CpuSimulation::new(&SimulationDescriptor::default()).unwrap();
+        let result = import(&mut simulation, bytes.as_slice(), &ImportOptions::default());
+        assert!(matches!(
+            result,
+            Err(ImportError::InvalidSize([4096, 4096]))
+        ));
+        assert_eq!(simulation.size(), SimulationDescriptor::default().size);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod backend;
+pub mod camera;
+pub mod cell;
+pub mod cpu;
+pub mod element;
+pub mod history;
+pub mod image;
+pub mod life;
+pub mod recording;
+pub mod save;
+pub mod simulation;
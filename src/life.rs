@@ -0,0 +1,130 @@
+use std::{error, fmt, str::FromStr};
+
+/// An outer-totalistic rule in B/S notation, such as `B3/S23` for Conway's Game of Life.
+///
+/// Bit `n` of `birth` is set when a void cell with `n` live neighbours is born, and bit `n` of
+/// `survival` is set when a live cell with `n` live neighbours survives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: u32,
+    pub survival: u32,
+}
+
+impl Rule {
+    pub const CONWAY: Self = Self {
+        birth: 1 << 3,
+        survival: (1 << 2) | (1 << 3),
+    };
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseRuleError {
+    InvalidFormat,
+    InvalidNeighbourCount(char),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "rule must be formatted as B<digits>/S<digits>"),
+            Self::InvalidNeighbourCount(count) => {
+                write!(f, "neighbour count '{count}' is not between 0 and 8")
+            }
+        }
+    }
+}
+
+impl error::Error for ParseRuleError {}
+
+fn parse_neighbour_counts(value: &str) -> Result<u32, ParseRuleError> {
+    value
+        .chars()
+        .try_fold(0, |counts, count| match count.to_digit(10) {
+            Some(digit) if digit <= 8 => Ok(counts | (1 << digit)),
+            _ => Err(ParseRuleError::InvalidNeighbourCount(count)),
+        })
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (birth, survival) = value.split_once('/').ok_or(ParseRuleError::InvalidFormat)?;
+        let birth = birth
+            .strip_prefix(['B', 'b'])
+            .ok_or(ParseRuleError::InvalidFormat)?;
+        let survival = survival
+            .strip_prefix(['S', 's'])
+            .ok_or(ParseRuleError::InvalidFormat)?;
+        Ok(Self {
+            birth: parse_neighbour_counts(birth)?,
+            survival: parse_neighbour_counts(survival)?,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |mask: u32| -> String {
+            (0..=8)
+                .filter(|count| mask & (1 << count) != 0)
+                .map(|count| char::from(b'0' + count as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!("B3/S23".parse(), Ok(Rule::CONWAY));
+        assert_eq!("b3/s23".parse(), Ok(Rule::CONWAY));
+        assert_eq!(
+            "B36/S23".parse(),
+            Ok(Rule {
+                birth: (1 << 3) | (1 << 6),
+                survival: (1 << 2) | (1 << 3),
+            })
+        );
+        assert_eq!(
+            "B/S".parse(),
+            Ok(Rule {
+                birth: 0,
+                survival: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert_eq!("B3S23".parse::<Rule>(), Err(ParseRuleError::InvalidFormat));
+        assert_eq!("3/23".parse::<Rule>(), Err(ParseRuleError::InvalidFormat));
+        assert_eq!("S23/B3".parse::<Rule>(), Err(ParseRuleError::InvalidFormat));
+        assert_eq!(
+            "B39/S23".parse::<Rule>(),
+            Err(ParseRuleError::InvalidNeighbourCount('9'))
+        );
+        assert_eq!(
+            "B3/S2x".parse::<Rule>(),
+            Err(ParseRuleError::InvalidNeighbourCount('x'))
+        );
+    }
+
+    #[test]
+    fn displays_rules() {
+        assert_eq!(Rule::CONWAY.to_string(), "B3/S23");
+        for rule in ["B36/S23", "B012345678/S012345678", "B/S"] {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+    }
+}
@@ -1,10 +1,22 @@
-mod simulation;
-
-use simulation::Simulation;
-use std::{rc::Rc, time};
+use casim::{
+    backend::{Brush, Mode, SimulationDescriptor},
+    camera::Camera,
+    element::{self, CellId, Element},
+    history::History,
+    image::ImportOptions,
+    life::Rule,
+    recording::{Recorder, RecordingOptions},
+    simulation::Simulation,
+};
+use std::{
+    env, mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time,
+};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{Event, MouseButton, MouseScrollDelta, StartCause, WindowEvent},
+    event::{Event, Modifiers, MouseButton, MouseScrollDelta, StartCause, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{KeyCode, PhysicalKey},
     window::Window,
@@ -28,16 +40,240 @@ impl UpdateMode {
     }
 }
 
-const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize::new(900, 900);
+const WINDOW_EXTENT: u32 = 900;
+const SAVE_PATH: &str = "world.casim";
+const IMAGE_PATH: &str = "world.png";
+const SCREENSHOT_PATH: &str = "screenshot.png";
+const SCREENSHOT_SCALE: u32 = 4;
+const RECORDING_PATH: &str = "recording.gif";
 const FRAMES_PER_SECOND: f32 = 144.0;
+const ZOOM_STEP: f32 = 1.25;
+// Hotter than every element's `heats_above`, so the heat brush can melt anything that melts.
+const HEAT_BRUSH_TEMPERATURE: f32 = 2000.0;
+const COOL_BRUSH_TEMPERATURE: f32 = -50.0;
+const BRUSH_KEYS: [KeyCode; 10] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+];
+
+fn brush_elements() -> impl Iterator<Item = &'static Element> + Clone {
+    element::ELEMENTS
+        .iter()
+        .filter(|element| element.id != CellId::Void)
+}
+
+fn cycle_brush_element(cell_id: CellId, offset: isize) -> CellId {
+    let count = brush_elements().count() as isize;
+    let index = brush_elements()
+        .position(|element| element.id == cell_id)
+        .unwrap_or(0) as isize;
+    brush_elements()
+        .nth((index + offset).rem_euclid(count) as usize)
+        .map_or(cell_id, |element| element.id)
+}
+
+fn next_mode(mode: Mode, rule: Rule) -> Mode {
+    match mode {
+        Mode::Sand => Mode::Life(rule),
+        Mode::Life(_) => Mode::SandAndLife(rule),
+        Mode::SandAndLife(_) => Mode::Sand,
+    }
+}
+
+fn parse_size(value: &str) -> Option<[u32; 2]> {
+    let (width, height) = value.split_once('x')?;
+    let size = [width.parse().ok()?, height.parse().ok()?];
+    size.iter().all(|&length| length > 0).then_some(size)
+}
+
+fn start_recording(
+    simulation: &Simulation,
+    path: &Path,
+    options: RecordingOptions,
+) -> Option<Recorder> {
+    match Recorder::new(simulation, path, options) {
+        Ok(recorder) => {
+            log::info!("recording to {}", path.display());
+            Some(recorder)
+        }
+        Err(err) => {
+            log::error!("failed to start recording! {err}");
+            None
+        }
+    }
+}
+
+fn finish_recording(recorder: Recorder, path: &Path) {
+    let frames = recorder.frames();
+    match recorder.finish() {
+        Ok(()) => log::info!("recorded {frames} frames to {}", path.display()),
+        Err(err) => log::error!("failed to finish recording! {err}"),
+    }
+}
+
+/// Runs the simulation without a window for as long as the recording lasts.
+fn record_headless(
+    descriptor: &SimulationDescriptor,
+    load_path: Option<&Path>,
+    path: &Path,
+    options: RecordingOptions,
+) {
+    let mut simulation = match Simulation::new_headless(descriptor) {
+        Ok(simulation) => simulation,
+        Err(err) => {
+            log::error!("failed to create simulation! {err}");
+            return;
+        }
+    };
+    if let Some(load_path) = load_path {
+        if let Err(err) = simulation.load(load_path) {
+            log::error!("failed to load world! {err}");
+            return;
+        }
+    }
+    let Some(mut recorder) = start_recording(&simulation, path, options) else {
+        return;
+    };
+    while !recorder.finished() {
+        simulation.step();
+        if let Err(err) = recorder.record_step(&simulation) {
+            log::error!("failed to record frame! {err}");
+            return;
+        }
+    }
+    finish_recording(recorder, path);
+}
+
+fn cursor_cell_position(
+    simulation: &Simulation,
+    view_position: [f32; 2],
+    cursor_position: [u32; 2],
+) -> [u32; 2] {
+    simulation
+        .camera()
+        .cell_position(simulation.size(), view_position)
+        .unwrap_or(cursor_position)
+}
+
+fn window_size(world_size: [u32; 2]) -> PhysicalSize<u32> {
+    let scale = WINDOW_EXTENT as f64 / world_size[0].max(world_size[1]) as f64;
+    PhysicalSize::new(
+        ((world_size[0] as f64 * scale) as u32).max(1),
+        ((world_size[1] as f64 * scale) as u32).max(1),
+    )
+}
 
 fn main() {
     env_logger::init();
+    let mut descriptor = SimulationDescriptor::default();
+    let mut recording_options = RecordingOptions::default();
+    let mut recording_path = None;
+    let mut load_path = None;
+    let mut headless = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let Some(seed) = args.next().and_then(|value| value.parse().ok()) else {
+                    log::error!("--seed requires an unsigned integer");
+                    return;
+                };
+                descriptor.seed = seed;
+            }
+            "--rule" => {
+                let Some(rule) = args.next().and_then(|value| value.parse().ok()) else {
+                    log::error!(
+                        "--rule requires a rule formatted as B<digits>/S<digits>, e.g. B3/S23"
+                    );
+                    return;
+                };
+                descriptor.mode = Mode::Life(rule);
+            }
+            "--record" => {
+                let Some(path) = args.next() else {
+                    log::error!("--record requires a path");
+                    return;
+                };
+                recording_path = Some(PathBuf::from(path));
+            }
+            "--stride" => {
+                let Some(stride) = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&stride| stride > 0)
+                else {
+                    log::error!("--stride requires a positive integer");
+                    return;
+                };
+                recording_options.stride = stride;
+            }
+            "--scale" => {
+                let Some(scale) = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&scale| scale > 0)
+                else {
+                    log::error!("--scale requires a positive integer");
+                    return;
+                };
+                recording_options.scale = scale;
+            }
+            "--duration" => {
+                let Some(duration) = args.next().and_then(|value| value.parse().ok()) else {
+                    log::error!("--duration requires an unsigned integer");
+                    return;
+                };
+                recording_options.duration = duration;
+            }
+            "--load" => {
+                let Some(path) = args.next() else {
+                    log::error!("--load requires a path");
+                    return;
+                };
+                load_path = Some(PathBuf::from(path));
+            }
+            "--fallback-adapter" => {
+                descriptor.force_fallback_adapter = true;
+            }
+            "--headless" => {
+                headless = true;
+            }
+            _ if arg.starts_with('-') => {
+                log::error!("unknown flag {arg}");
+                return;
+            }
+            _ => {
+                let Some(size) = parse_size(&arg) else {
+                    log::error!(
+                        "unknown argument {arg}, expected a world size formatted as WIDTHxHEIGHT"
+                    );
+                    return;
+                };
+                descriptor.size = size;
+            }
+        }
+    }
+    if headless {
+        let Some(path) = recording_path else {
+            log::error!("--headless requires --record <path>");
+            return;
+        };
+        record_headless(&descriptor, load_path.as_deref(), &path, recording_options);
+        return;
+    }
     let event_loop = EventLoop::new().expect("new event loop");
     let window = Rc::new(Window::new(&event_loop).expect("new window"));
     window.set_resizable(false);
     window.set_title("Casim");
-    let _ = window.request_inner_size(WINDOW_SIZE);
+    let _ = window.request_inner_size(window_size(descriptor.size));
     if let Some(monitor) = event_loop.primary_monitor() {
         let monitor_size = monitor.size();
         let window_size = window.outer_size();
@@ -46,16 +282,42 @@ fn main() {
             (monitor_size.height - window_size.height) / 2,
         ));
     }
-    let mut simulation = Simulation::new(window.clone());
+    let mut simulation = match Simulation::new(window.clone(), &descriptor) {
+        Ok(simulation) => simulation,
+        Err(err) => {
+            log::error!("failed to create simulation! {err}");
+            return;
+        }
+    };
+    if let Some(load_path) = &load_path {
+        match simulation.load(load_path) {
+            Ok(()) => {
+                simulation.set_camera(Camera::default());
+                let _ = window.request_inner_size(window_size(simulation.size()));
+            }
+            Err(err) => log::error!("failed to load world! {err}"),
+        }
+    }
+    let mut recorder = recording_path
+        .as_deref()
+        .and_then(|path| start_recording(&simulation, path, recording_options));
+    let recording_path = recording_path.unwrap_or_else(|| PathBuf::from(RECORDING_PATH));
+    let life_rule = descriptor.mode.life_rule().unwrap_or_default();
     let mut exit = false;
     let mut window_focused = false;
     let mut polling = false;
+    let mut modifiers = Modifiers::default();
     let mut update_mode = UpdateMode::new_tick();
     let mut cursor_enabled = false;
     let mut cursor_radius = 1;
     let mut cursor_position = [0, 0];
-    let mut cursor_cell_id = simulation::CellId::Sand;
+    let mut cursor_view_position = [0.0, 0.0];
+    let mut panning = false;
+    let mut cursor_cell_id = CellId::Sand;
+    let mut cursor_temperature = None;
     let mut cursor_erase = false;
+    let mut history = History::default();
+    let mut shift_pause = false;
     event_loop
         .run(|event, event_loop| match event {
             Event::NewEvents(start_cause) => match start_cause {
@@ -73,25 +335,60 @@ fn main() {
                 WindowEvent::Resized(_) => {
                     simulation.reconfigure();
                 }
+                WindowEvent::ModifiersChanged(new_modifiers) => {
+                    modifiers = new_modifiers;
+                }
                 WindowEvent::CursorLeft { .. } => {
                     cursor_enabled = false;
                 }
                 WindowEvent::CursorMoved { position, .. } => {
                     let window_size = window.inner_size().cast::<f64>();
-                    let simulation_size = Simulation::SIZE.map(|value| value as f64);
-                    cursor_position = [
-                        ((position.x / window_size.width) * simulation_size[0]) as u32,
-                        (((window_size.height - position.y) / window_size.height)
-                            * simulation_size[1]) as u32,
+                    let view_position = [
+                        (position.x / window_size.width) as f32,
+                        ((window_size.height - position.y) / window_size.height) as f32,
                     ];
+                    if panning {
+                        let mut camera = simulation.camera();
+                        camera.pan(
+                            simulation.size(),
+                            [
+                                view_position[0] - cursor_view_position[0],
+                                view_position[1] - cursor_view_position[1],
+                            ],
+                        );
+                        simulation.set_camera(camera);
+                    }
+                    cursor_view_position = view_position;
+                    cursor_position =
+                        cursor_cell_position(&simulation, cursor_view_position, cursor_position);
                 }
                 WindowEvent::MouseInput { state, button, .. } => match button {
                     MouseButton::Left | MouseButton::Right => {
+                        if state.is_pressed() {
+                            history.begin_stroke(&simulation);
+                        } else {
+                            history.end_stroke(&simulation);
+                        }
                         cursor_enabled = state.is_pressed();
                         cursor_erase = button == MouseButton::Right;
                     }
+                    MouseButton::Middle => {
+                        panning = state.is_pressed();
+                    }
                     _ => {}
                 },
+                WindowEvent::MouseWheel { delta, .. } if modifiers.state().control_key() => {
+                    let factor = match delta {
+                        MouseScrollDelta::LineDelta(_, y) if y > 0.0 => ZOOM_STEP,
+                        MouseScrollDelta::LineDelta(_, y) if y < 0.0 => 1.0 / ZOOM_STEP,
+                        _ => return,
+                    };
+                    let mut camera = simulation.camera();
+                    camera.zoom_at(simulation.size(), cursor_view_position, factor);
+                    simulation.set_camera(camera);
+                    cursor_position =
+                        cursor_cell_position(&simulation, cursor_view_position, cursor_position);
+                }
                 WindowEvent::MouseWheel { delta, .. } => {
                     cursor_radius = match delta {
                         MouseScrollDelta::LineDelta(_, y) if y > 0.0 => cursor_radius + 1,
@@ -100,27 +397,138 @@ fn main() {
                     }
                     .clamp(1, 20);
                 }
-                WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() => {
+                // Left Shift is also held for Ctrl+Shift+Z, so it only pauses when it is released
+                // without another key pressed in between.
+                WindowEvent::KeyboardInput { event, .. }
+                    if event.physical_key == PhysicalKey::Code(KeyCode::ShiftLeft) =>
+                {
+                    if event.state.is_pressed() {
+                        if !event.repeat {
+                            shift_pause = !modifiers.state().control_key();
+                        }
+                    } else if mem::take(&mut shift_pause) {
+                        update_mode = match update_mode {
+                            UpdateMode::Tick { .. } => UpdateMode::new_step(),
+                            UpdateMode::Step { .. } => UpdateMode::new_tick(),
+                        }
+                    }
+                }
+                WindowEvent::KeyboardInput { event, .. }
+                    if event.state.is_pressed() && modifiers.state().control_key() =>
+                {
+                    shift_pause = false;
                     match event.physical_key {
-                        PhysicalKey::Code(KeyCode::ShiftLeft) => {
-                            update_mode = match update_mode {
-                                UpdateMode::Tick { .. } => UpdateMode::new_step(),
-                                UpdateMode::Step { .. } => UpdateMode::new_tick(),
+                        PhysicalKey::Code(KeyCode::KeyZ) => {
+                            if modifiers.state().shift_key() {
+                                history.redo(&mut simulation);
+                            } else {
+                                history.undo(&mut simulation);
+                            }
+                        }
+                        PhysicalKey::Code(KeyCode::KeyS) => match simulation.save(SAVE_PATH) {
+                            Ok(()) => log::info!("saved world to {SAVE_PATH}"),
+                            Err(err) => log::error!("failed to save world! {err}"),
+                        },
+                        PhysicalKey::Code(KeyCode::KeyO) => match simulation.load(SAVE_PATH) {
+                            Ok(()) => {
+                                log::info!("loaded world from {SAVE_PATH}");
+                                history.clear();
+                                simulation.set_camera(Camera::default());
+                                let _ = window.request_inner_size(window_size(simulation.size()));
+                            }
+                            Err(err) => log::error!("failed to load world! {err}"),
+                        },
+                        PhysicalKey::Code(KeyCode::KeyE) => match simulation.export_png(IMAGE_PATH)
+                        {
+                            Ok(()) => log::info!("exported world to {IMAGE_PATH}"),
+                            Err(err) => log::error!("failed to export world! {err}"),
+                        },
+                        PhysicalKey::Code(KeyCode::KeyP) => {
+                            let size = simulation.screenshot_size(SCREENSHOT_SCALE);
+                            match simulation.save_screenshot(SCREENSHOT_PATH, size) {
+                                Ok(()) => log::info!(
+                                    "saved {}x{} screenshot to {SCREENSHOT_PATH}",
+                                    size[0],
+                                    size[1]
+                                ),
+                                Err(err) => log::error!("failed to save screenshot! {err}"),
                             }
                         }
+                        PhysicalKey::Code(KeyCode::KeyR) => match recorder.take() {
+                            Some(recorder) => finish_recording(recorder, &recording_path),
+                            None => {
+                                recorder = start_recording(
+                                    &simulation,
+                                    &recording_path,
+                                    recording_options,
+                                );
+                            }
+                        },
+                        PhysicalKey::Code(KeyCode::KeyI) => {
+                            let options = ImportOptions {
+                                unknown_as_void: true,
+                                ..Default::default()
+                            };
+                            match simulation.import_png(IMAGE_PATH, &options) {
+                                Ok(()) => {
+                                    log::info!("imported world from {IMAGE_PATH}");
+                                    history.clear();
+                                    simulation.set_camera(Camera::default());
+                                    let _ =
+                                        window.request_inner_size(window_size(simulation.size()));
+                                }
+                                Err(err) => log::error!("failed to import world! {err}"),
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() => {
+                    shift_pause = false;
+                    match event.physical_key {
                         PhysicalKey::Code(KeyCode::Space) => {
                             if let UpdateMode::Step { requested } = &mut update_mode {
                                 *requested = true;
                             };
                         }
-                        PhysicalKey::Code(KeyCode::Digit1) => {
-                            cursor_cell_id = simulation::CellId::Rock;
+                        PhysicalKey::Code(KeyCode::BracketLeft) => {
+                            cursor_cell_id = cycle_brush_element(cursor_cell_id, -1);
+                            cursor_temperature = None;
+                            log::info!("brush: {}", cursor_cell_id.element().name);
+                        }
+                        PhysicalKey::Code(KeyCode::BracketRight) => {
+                            cursor_cell_id = cycle_brush_element(cursor_cell_id, 1);
+                            cursor_temperature = None;
+                            log::info!("brush: {}", cursor_cell_id.element().name);
+                        }
+                        PhysicalKey::Code(KeyCode::KeyL) => {
+                            let mode = next_mode(simulation.mode(), life_rule);
+                            simulation.set_mode(mode);
+                            log::info!("mode: {mode}");
+                        }
+                        PhysicalKey::Code(KeyCode::KeyV) => {
+                            let overlay = simulation.overlay().next();
+                            simulation.set_overlay(overlay);
+                            log::info!("overlay: {overlay:?}");
                         }
-                        PhysicalKey::Code(KeyCode::Digit2) => {
-                            cursor_cell_id = simulation::CellId::Sand;
+                        PhysicalKey::Code(KeyCode::KeyH) => {
+                            cursor_temperature = Some(HEAT_BRUSH_TEMPERATURE);
+                            log::info!("brush: heat to {HEAT_BRUSH_TEMPERATURE}°C");
                         }
-                        PhysicalKey::Code(KeyCode::Digit3) => {
-                            cursor_cell_id = simulation::CellId::Water;
+                        PhysicalKey::Code(KeyCode::KeyC) => {
+                            cursor_temperature = Some(COOL_BRUSH_TEMPERATURE);
+                            log::info!("brush: cool to {COOL_BRUSH_TEMPERATURE}°C");
+                        }
+                        PhysicalKey::Code(code) => {
+                            let element = BRUSH_KEYS
+                                .iter()
+                                .position(|&key| key == code)
+                                .and_then(|index| brush_elements().nth(index));
+                            if let Some(element) = element {
+                                cursor_cell_id = element.id;
+                                cursor_temperature = None;
+                                log::info!("brush: {}", element.name);
+                            }
                         }
                         _ => {}
                     }
@@ -137,12 +545,15 @@ fn main() {
                 if !polling || !window_focused {
                     return;
                 }
-                let cell_id = if cursor_erase {
-                    simulation::CellId::Void
-                } else {
-                    cursor_cell_id
+                let brush = match cursor_temperature {
+                    _ if cursor_erase => Brush::Cell(CellId::Void),
+                    Some(temperature) => Brush::Temperature(temperature),
+                    None => Brush::Cell(cursor_cell_id),
                 };
-                simulation.set_cursor(cursor_enabled, cursor_radius, cursor_position, cell_id);
+                if cursor_enabled {
+                    history.extend_stroke(cursor_position, cursor_radius);
+                }
+                simulation.set_cursor(cursor_enabled, cursor_radius, cursor_position, brush);
                 window.request_redraw();
                 match &mut update_mode {
                     UpdateMode::Tick { next } => {
@@ -160,6 +571,16 @@ fn main() {
                     }
                 }
                 simulation.step();
+                if let Some(active) = &mut recorder {
+                    if let Err(err) = active.record_step(&simulation) {
+                        log::error!("failed to record frame! {err}");
+                        recorder = None;
+                    } else if active.finished() {
+                        if let Some(recorder) = recorder.take() {
+                            finish_recording(recorder, &recording_path);
+                        }
+                    }
+                }
             }
             _ => {}
         })
@@ -0,0 +1,177 @@
+use crate::{image, simulation::Simulation};
+use std::{
+    error, fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+// GIF frame delays are in hundredths of a second, so this plays recordings at 25 frames per
+// second no matter how fast the simulation ran.
+const GIF_FRAME_DELAY: u16 = 4;
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordingOptions {
+    /// Records a frame every `stride` steps.
+    pub stride: u32,
+    /// Pixels per cell, see `Simulation::screenshot_size`.
+    pub scale: u32,
+    /// How many steps to record.
+    pub duration: u32,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            stride: 1,
+            scale: 2,
+            duration: 300,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Png(err) => write!(f, "{err}"),
+            Self::Gif(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl error::Error for RecordingError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Png(err) => Some(err),
+            Self::Gif(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for RecordingError {
+    fn from(err: png::EncodingError) -> Self {
+        Self::Png(err)
+    }
+}
+
+impl From<gif::EncodingError> for RecordingError {
+    fn from(err: gif::EncodingError) -> Self {
+        Self::Gif(err)
+    }
+}
+
+enum Output {
+    Gif(gif::Encoder<BufWriter<File>>),
+    PngSequence(PathBuf),
+}
+
+/// Records the rendered view of a simulation, as an animated GIF when the path ends in `.gif` and
+/// as numbered PNG files in the directory at the path otherwise.
+///
+/// Every frame has the size picked when the recording starts, and the first frame shows the
+/// simulation before the first recorded step.
+pub struct Recorder {
+    output: Output,
+    options: RecordingOptions,
+    size: [u32; 2],
+    steps: u32,
+    frames: u32,
+}
+
+impl Recorder {
+    pub fn new(
+        simulation: &Simulation,
+        path: impl AsRef<Path>,
+        options: RecordingOptions,
+    ) -> Result<Self, RecordingError> {
+        let path = path.as_ref();
+        // GIF sizes are 16 bit.
+        let size = simulation
+            .screenshot_size(options.scale)
+            .map(|length| length.min(u16::MAX as u32));
+        let output = if path.extension().is_some_and(|extension| extension == "gif") {
+            let file = BufWriter::new(File::create(path)?);
+            let mut encoder = gif::Encoder::new(file, size[0] as u16, size[1] as u16, &[])?;
+            encoder.set_repeat(gif::Repeat::Infinite)?;
+            Output::Gif(encoder)
+        } else {
+            fs::create_dir_all(path)?;
+            Output::PngSequence(path.to_path_buf())
+        };
+        let mut recorder = Self {
+            output,
+            options,
+            size,
+            steps: 0,
+            frames: 0,
+        };
+        recorder.record_frame(simulation)?;
+        Ok(recorder)
+    }
+
+    /// Call after every step of the simulation.
+    pub fn record_step(&mut self, simulation: &Simulation) -> Result<(), RecordingError> {
+        if self.finished() {
+            return Ok(());
+        }
+        self.steps += 1;
+        if self.steps.is_multiple_of(self.options.stride.max(1)) {
+            self.record_frame(simulation)?;
+        }
+        Ok(())
+    }
+
+    pub fn finished(&self) -> bool {
+        self.steps >= self.options.duration
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn finish(self) -> Result<(), RecordingError> {
+        if let Output::Gif(encoder) = self.output {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(())
+    }
+
+    fn record_frame(&mut self, simulation: &Simulation) -> Result<(), RecordingError> {
+        let mut pixels = simulation.screenshot(self.size);
+        match &mut self.output {
+            Output::Gif(encoder) => {
+                let mut frame = gif::Frame::from_rgba_speed(
+                    self.size[0] as u16,
+                    self.size[1] as u16,
+                    &mut pixels,
+                    GIF_QUANTIZATION_SPEED,
+                );
+                frame.delay = GIF_FRAME_DELAY;
+                encoder.write_frame(&frame)?;
+            }
+            Output::PngSequence(directory) => {
+                let path = directory.join(format!("frame_{:05}.png", self.frames));
+                let file = BufWriter::new(File::create(path)?);
+                image::write_rgba(file, self.size, &pixels)?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{
+    backend::{self, Anchor, Backend, SizeError},
+    cell::{self, Cell},
+    element::{self, CellId},
+};
+use std::{
+    collections::HashMap,
+    error, fmt,
+    io::{self, Read, Write},
+};
+
+pub const MAGIC: [u8; 8] = *b"CASIM\0\0\0";
+pub const VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    InvalidSize([u32; 2]),
+    InvalidElementName,
+    UnknownElement(String),
+    InvalidCellId(u32),
+    InvalidRunLength,
+    InvalidTemperature(i32),
+    InvalidVelocity(u32),
+    TrailingData,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::InvalidMagic => write!(f, "not a casim world file"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported version {version}"),
+            Self::InvalidSize([width, height]) => write!(f, "invalid world size {width}x{height}"),
+            Self::InvalidElementName => write!(f, "element name is not valid UTF-8"),
+            Self::UnknownElement(name) => write!(f, "unknown element \"{name}\""),
+            Self::InvalidCellId(id) => write!(f, "cell id {id:#04x} is not in the element table"),
+            Self::InvalidRunLength => write!(f, "cell runs do not cover the world"),
+            Self::InvalidTemperature(temperature) => write!(
+                f,
+                "temperature {}°C is out of range",
+                element::celsius(*temperature)
+            ),
+            Self::InvalidVelocity(velocity) => write!(f, "velocity {velocity} is out of range"),
+            Self::TrailingData => write!(f, "unexpected data after the last cell run"),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<SizeError> for LoadError {
+    fn from(SizeError(size): SizeError) -> Self {
+        Self::InvalidSize(size)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub size: [u32; 2],
+    pub state: u32,
+    pub cells: Vec<Cell>,
+    pub temperatures: Vec<i32>,
+}
+
+impl Snapshot {
+    pub fn capture(backend: &impl Backend) -> Self {
+        Self {
+            size: backend.size(),
+            state: backend.state(),
+            cells: backend.read_cells(),
+            temperatures: backend.read_temperatures(),
+        }
+    }
+
+    pub fn restore(&self, backend: &mut impl Backend) -> Result<(), SizeError> {
+        if backend.size() != self.size {
+            backend.resize(self.size, [Anchor::Start; 2])?;
+        }
+        backend.set_state(self.state);
+        backend.write_cells(&self.cells);
+        backend.write_temperatures(&self.temperatures);
+        Ok(())
+    }
+
+    // Void cells never feed their state, temperature or velocity back into other cells, so they
+    // are only stored for non-void cells.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        write_u32(&mut writer, VERSION)?;
+        write_u32(&mut writer, self.size[0])?;
+        write_u32(&mut writer, self.size[1])?;
+        write_u32(&mut writer, self.state)?;
+        write_u32(&mut writer, element::ELEMENTS.len() as u32)?;
+        for element in element::ELEMENTS {
+            write_u32(&mut writer, element.id as u32)?;
+            writer.write_all(&[element.name.len() as u8])?;
+            writer.write_all(element.name.as_bytes())?;
+        }
+        let mut temperatures = self.temperatures.iter();
+        for run in self.cells.chunk_by(|a, b| a.id == b.id) {
+            let id = run[0].id;
+            write_u32(&mut writer, run.len() as u32)?;
+            write_u32(&mut writer, id as u32)?;
+            for (cell, &temperature) in run.iter().zip(temperatures.by_ref()) {
+                if id != CellId::Void {
+                    write_u32(&mut writer, cell.state)?;
+                    write_u32(&mut writer, temperature as u32)?;
+                    write_u32(&mut writer, cell.velocity)?;
+                }
+            }
+        }
+        writer.flush()
+    }
+
+    // Version 1 files have no temperatures, so their cells start at the element's spawn
+    // temperature, and cells from files before version 3 start at rest.
+    pub fn read(mut reader: impl Read) -> Result<Self, LoadError> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(LoadError::InvalidMagic);
+        }
+        let version = read_u32(&mut reader)?;
+        if version == 0 || version > VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let size = [read_u32(&mut reader)?, read_u32(&mut reader)?];
+        backend::check_size(size)?;
+        let state = read_u32(&mut reader)?;
+        let element_count = read_u32(&mut reader)?;
+        let mut cell_ids = HashMap::new();
+        for _ in 0..element_count {
+            let id = read_u32(&mut reader)?;
+            let mut name_length = [0; 1];
+            reader.read_exact(&mut name_length)?;
+            let mut name = vec![0; name_length[0] as usize];
+            reader.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| LoadError::InvalidElementName)?;
+            let element = element::find(&name).ok_or(LoadError::UnknownElement(name))?;
+            cell_ids.insert(id, element.id);
+        }
+        let temperature_range = element::temperature(element::MIN_TEMPERATURE)
+            ..=element::temperature(element::MAX_TEMPERATURE);
+        let cell_count = size[0] as usize * size[1] as usize;
+        let mut cells = Vec::with_capacity(cell_count);
+        let mut temperatures = Vec::with_capacity(cell_count);
+        while cells.len() < cell_count {
+            let run_length = read_u32(&mut reader)? as usize;
+            if run_length == 0 || cells.len() + run_length > cell_count {
+                return Err(LoadError::InvalidRunLength);
+            }
+            let file_id = read_u32(&mut reader)?;
+            let id = *cell_ids
+                .get(&file_id)
+                .ok_or(LoadError::InvalidCellId(file_id))?;
+            let spawn_temperature = element::temperature(id.element().temperature);
+            for _ in 0..run_length {
+                let (state, temperature, velocity) = if id == CellId::Void {
+                    (0, spawn_temperature, 0)
+                } else if version < 2 {
+                    (read_u32(&mut reader)?, spawn_temperature, 0)
+                } else if version < 3 {
+                    (read_u32(&mut reader)?, read_u32(&mut reader)? as i32, 0)
+                } else {
+                    (
+                        read_u32(&mut reader)?,
+                        read_u32(&mut reader)? as i32,
+                        read_u32(&mut reader)?,
+                    )
+                };
+                if !temperature_range.contains(&temperature) {
+                    return Err(LoadError::InvalidTemperature(temperature));
+                }
+                if velocity > cell::MAX_VELOCITY {
+                    return Err(LoadError::InvalidVelocity(velocity));
+                }
+                cells.push(Cell {
+                    id,
+                    state,
+                    velocity,
+                });
+                temperatures.push(temperature);
+            }
+        }
+        if reader.read(&mut [0; 1])? != 0 {
+            return Err(LoadError::TrailingData);
+        }
+        Ok(Self {
+            size,
+            state,
+            cells,
+            temperatures,
+        })
+    }
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{Brush, SimulationDescriptor},
+        cpu::CpuSimulation,
+    };
+
+    fn header(size: [u32; 2], elements: &[(u32, &str)]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for value in [VERSION, size[0], size[1], 0, elements.len() as u32] {
+            write_u32(&mut bytes, value).unwrap();
+        }
+        for (id, name) in elements {
+            write_u32(&mut bytes, *id).unwrap();
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name.as_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut simulation = CpuSimulation::new(&SimulationDescriptor {
+            size: [20, 12],
+            seed: 4,
+            ..Default::default()
+        })
+        .unwrap();
+        for (position, cell_id) in [([5, 8], CellId::Sand), ([14, 8], CellId::Lava)] {
+            simulation.set_cursor(true, 3, position, Brush::Cell(cell_id));
+        }
+        simulation.set_cursor(false, 1, [0, 0], Brush::default());
+        for _ in 0..5 {
+            simulation.step();
+        }
+        let snapshot = Snapshot::capture(&simulation);
+        let mut bytes = Vec::new();
+        snapshot.write(&mut bytes).unwrap();
+        let loaded = Snapshot::read(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.size, snapshot.size);
+        assert_eq!(loaded.state, snapshot.state);
+        let cells = snapshot.cells.iter().zip(&snapshot.temperatures);
+        let loaded_cells = loaded.cells.iter().zip(&loaded.temperatures);
+        for ((cell, temperature), (loaded_cell, loaded_temperature)) in cells.zip(loaded_cells) {
+            assert_eq!(loaded_cell.id, cell.id);
+            if cell.id != CellId::Void {
+                assert_eq!(loaded_cell, cell);
+                assert_eq!(loaded_temperature, temperature);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_cell_id() {
+        let mut bytes = header([2, 2], &[(0, "void")]);
+        write_u32(&mut bytes, 4).unwrap();
+        write_u32(&mut bytes, 7).unwrap();
+        let result = Snapshot::read(bytes.as_slice());
+        assert!(matches!(result, Err(LoadError::InvalidCellId(7))));
+    }
+
+    #[test]
+    fn rejects_unknown_element() {
+        let bytes = header([2, 2], &[(0, "void"), (1, "unobtainium")]);
+        let result = Snapshot::read(bytes.as_slice());
+        assert!(matches!(result, Err(LoadError::UnknownElement(name)) if name == "unobtainium"));
+    }
+
+    #[test]
+    fn rejects_invalid_size() {
+        for size in [[0, 4], [4, 0], [1 << 16, 1 << 16]] {
+            let result = Snapshot::read(header(size, &[]).as_slice());
+            assert!(matches!(result, Err(LoadError::InvalidSize(invalid)) if invalid == size));
+        }
+    }
+
+    #[test]
+    fn rejects_run_past_the_world() {
+        let mut bytes = header([2, 2], &[(0, "void")]);
+        write_u32(&mut bytes, 5).unwrap();
+        write_u32(&mut bytes, 0).unwrap();
+        let result = Snapshot::read(bytes.as_slice());
+        assert!(matches!(result, Err(LoadError::InvalidRunLength)));
+    }
+}
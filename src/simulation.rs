@@ -1,24 +1,25 @@
-use std::{mem, rc::Rc};
+use crate::{
+    backend::{self, Anchor, Backend, Brush, Mode, SimulationDescriptor, SizeError},
+    camera::Camera,
+    cell::{self, hash_u32, Cell},
+    element::{self, CellId},
+    image::{self, ImportError, ImportOptions},
+    life::Rule,
+    save::{LoadError, Snapshot},
+};
+use std::{
+    error,
+    fmt::{self, Write as _},
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    mem,
+    path::Path,
+    rc::Rc,
+    sync::{mpsc, Arc},
+};
 use wgpu::util::DeviceExt;
 use winit::{dpi::PhysicalSize, window::Window};
 
-#[repr(u32)]
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
-pub enum CellId {
-    #[default]
-    Void = 0x00,
-    Rock = 0x01,
-    Sand = 0x02,
-    Water = 0x03,
-}
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct Cell {
-    id: CellId,
-    state: u32,
-}
-
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Zeroable, bytemuck::Pod)]
 struct Cursor {
@@ -26,9 +27,16 @@ struct Cursor {
     radius: u32,
     position: [u32; 2],
     cell_id: u32,
+    mode: u32,
+    temperature: i32,
     _p0: u32,
 }
 
+impl Cursor {
+    const MODE_CELL: u32 = 0;
+    const MODE_TEMPERATURE: u32 = 1;
+}
+
 impl Default for Cursor {
     fn default() -> Self {
         Self {
@@ -36,6 +44,8 @@ impl Default for Cursor {
             radius: 1,
             position: [0, 0],
             cell_id: 0,
+            mode: Self::MODE_CELL,
+            temperature: 0,
             _p0: 0,
         }
     }
@@ -49,52 +59,187 @@ struct World {
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Zeroable, bytemuck::Pod)]
+struct View {
+    offset: [f32; 2],
+    zoom: f32,
+    overlay: u32,
+    cursor_visible: u32,
+    _p0: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Zeroable, bytemuck::Pod)]
+struct Activity {
+    key: u32,
+    heat: u32,
+    pass_index: u32,
+}
+
+/// A debug view drawn instead of the plain element colours.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overlay {
+    #[default]
+    None = 0,
+    /// The random value, lifetime or growth stage in `Cell.state`, as red, green and blue bytes.
+    State = 1,
+    /// Cells that changed their element or moved, fading out over the following steps.
+    Activity = 2,
+    /// Which of the nine checkerboard passes last updated each cell.
+    Pass = 3,
+    /// Temperatures from blue at -50°C to red at 1500°C.
+    Temperature = 4,
+}
+
+impl Overlay {
+    pub const ALL: [Self; 5] = [
+        Self::None,
+        Self::State,
+        Self::Activity,
+        Self::Pass,
+        Self::Temperature,
+    ];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    fn shader_name(self) -> &'static str {
+        match self {
+            Self::None => "OVERLAY_NONE",
+            Self::State => "OVERLAY_STATE",
+            Self::Activity => "OVERLAY_ACTIVITY",
+            Self::Pass => "OVERLAY_PASS",
+            Self::Temperature => "OVERLAY_TEMPERATURE",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Zeroable, bytemuck::Pod)]
 struct Push {
     local_offset: [u32; 2],
     state: u32,
+    substep: u32,
+    life_rule: [u32; 2],
+    pairing: u32,
     _p0: u32,
 }
 
-pub struct Simulation {
+#[derive(Debug)]
+pub enum SimulationError {
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    Size(SizeError),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAdapter => write!(f, "no compatible graphics adapter found"),
+            Self::RequestDevice(err) => write!(f, "{err}"),
+            Self::Size(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl error::Error for SimulationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::RequestDevice(err) => Some(err),
+            Self::Size(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for SimulationError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(err)
+    }
+}
+
+impl From<SizeError> for SimulationError {
+    fn from(err: SizeError) -> Self {
+        Self::Size(err)
+    }
+}
+
+struct Presentation {
     window: Rc<Window>,
-    instance: wgpu::Instance,
     surface: wgpu::Surface<'static>,
     surface_format: wgpu::TextureFormat,
     surface_present_mode: wgpu::PresentMode,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+pub struct Simulation {
+    presentation: Option<Presentation>,
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    size: [u32; 2],
     world_buffer: wgpu::Buffer,
     cursor_buffer: wgpu::Buffer,
+    view_buffer: wgpu::Buffer,
+    activities_buffer: wgpu::Buffer,
     cells_buffer_size: u64,
     cells_input_buffer: wgpu::Buffer,
     cells_output_buffer: wgpu::Buffer,
+    temperatures_buffer_size: u64,
+    temperatures_input_buffer: wgpu::Buffer,
+    temperatures_output_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
+    module: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
     cursor_pipeline: wgpu::ComputePipeline,
+    heat_pipeline: wgpu::ComputePipeline,
+    life_pipeline: wgpu::ComputePipeline,
     step_pipeline: wgpu::ComputePipeline,
-    render_pipeline: wgpu::RenderPipeline,
+    activity_pipeline: wgpu::ComputePipeline,
+    offscreen_pipeline: wgpu::RenderPipeline,
+    offscreen_texture: wgpu::Texture,
+    camera: Camera,
+    overlay: Overlay,
+    mode: Mode,
     state: u32,
 }
 
 impl Simulation {
-    pub const SIZE: [u32; 2] = [128, 128];
+    pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
 
-    pub fn new(window: Rc<Window>) -> Self {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN,
-            ..Default::default()
-        });
-        let surface = {
-            let target = unsafe { wgpu::SurfaceTargetUnsafe::from_window(&window) }
-                .expect("valid surface target");
-            unsafe { instance.create_surface_unsafe(target) }
-        }
-        .expect("new surface");
+    pub fn new(
+        window: Rc<Window>,
+        descriptor: &SimulationDescriptor,
+    ) -> Result<Self, SimulationError> {
+        let instance = Self::create_instance();
+        let surface = Self::create_surface(&instance, &window);
+        let mut simulation = Self::with_instance(instance, Some(&surface), descriptor)?;
+        simulation.attach_surface(window, surface);
+        Ok(simulation)
+    }
+
+    /// Creates a simulation that is never presented, e.g. for batch runs. Fails instead of
+    /// panicking when no adapter is available, so callers can fall back to `CpuSimulation`.
+    pub fn new_headless(descriptor: &SimulationDescriptor) -> Result<Self, SimulationError> {
+        let instance = Self::create_instance();
+        Self::with_instance(instance, None, descriptor)
+    }
+
+    fn with_instance(
+        instance: wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface<'static>>,
+        descriptor: &SimulationDescriptor,
+    ) -> Result<Self, SimulationError> {
         let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::HighPerformance,
-            force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
+            force_fallback_adapter: descriptor.force_fallback_adapter,
+            compatible_surface,
         }))
-        .expect("request adapter");
+        .ok_or(SimulationError::NoAdapter)?;
+        log::info!("using adapter {:?}", adapter.get_info());
         let (device, queue) = pollster::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
@@ -105,50 +250,32 @@ impl Simulation {
                 },
             },
             None,
-        ))
-        .expect("valid device, queue");
-        let capabilities = surface.get_capabilities(&adapter);
-        let surface_format = *capabilities.formats.get(0).expect("texture format");
-        let surface_present_mode = wgpu::PresentMode::AutoNoVsync;
-        let surface_config =
-            Self::create_surface_config(surface_format, window.inner_size(), surface_present_mode);
-        surface.configure(&device, &surface_config);
-        let world = World { size: Self::SIZE };
+        ))?;
+        let size = descriptor.size;
+        Self::check_size(&device, size)?;
+        let world = World { size };
         let world_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("World"),
             contents: bytemuck::bytes_of(&world),
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
         });
-        let cursor = Cursor {
-            enabled: 0,
-            radius: 1,
-            position: [0, 0],
-            cell_id: 0,
-            _p0: 0,
-        };
+        let cursor = Cursor::default();
         let cursor_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Cursor"),
             contents: bytemuck::bytes_of(&cursor),
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
         });
-        let cells_buffer_size = wgpu::util::align_to(
-            mem::size_of::<Cell>() as u64 * (Self::SIZE[0] * Self::SIZE[1]) as u64,
-            wgpu::COPY_BUFFER_ALIGNMENT,
-        );
-        let cells_input_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Cells Input"),
-            size: cells_buffer_size,
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
-        let cells_output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Cells Output"),
-            size: cells_buffer_size,
-            usage: wgpu::BufferUsages::COPY_SRC
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("View"),
+            size: mem::size_of::<View>() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
             mapped_at_creation: false,
         });
+        let activities_buffer = Self::create_activities_buffer(&device, size);
+        let (cells_buffer_size, cells_input_buffer, cells_output_buffer) =
+            Self::create_cells_buffers(&device, size);
+        let (temperatures_buffer_size, temperatures_input_buffer, temperatures_output_buffer) =
+            Self::create_temperatures_buffers(&device, size);
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
@@ -192,38 +319,62 @@ impl Simulation {
                     },
                     count: None,
                 },
-            ],
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        world_buffer.as_entire_buffer_binding(),
-                    ),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        cursor_buffer.as_entire_buffer_binding(),
-                    ),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        cells_input_buffer.as_entire_buffer_binding(),
-                    ),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        cells_output_buffer.as_entire_buffer_binding(),
-                    ),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
             ],
         });
+        let bind_group = Self::create_bind_group(
+            &device,
+            &bind_group_layout,
+            &[
+                &world_buffer,
+                &cursor_buffer,
+                &cells_input_buffer,
+                &cells_output_buffer,
+                &temperatures_input_buffer,
+                &temperatures_output_buffer,
+                &view_buffer,
+                &activities_buffer,
+            ],
+        );
         let range = 0..mem::size_of::<Push>() as u32;
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
@@ -233,79 +384,258 @@ impl Simulation {
                 range,
             }],
         });
-        let module = device.create_shader_module(wgpu::include_wgsl!("simulation.wgsl"));
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("simulation.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(Self::shader_source().into()),
+        });
         let cursor_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
             module: &module,
             entry_point: "compute_cursor",
         });
-        let step_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        let heat_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
             module: &module,
-            entry_point: "compute_step",
+            entry_point: "compute_heat",
         });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let activity_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &module,
-                entry_point: "vertex_main",
-                buffers: &[],
-            },
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            fragment: Some(wgpu::FragmentState {
-                module: &module,
-                entry_point: "fragment_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
+            module: &module,
+            entry_point: "compute_activity",
         });
-        let state = 0;
-        Self {
-            window,
+        let life_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: "compute_life",
+        });
+        let step_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: "compute_step",
+        });
+        let offscreen_pipeline = Self::create_render_pipeline(
+            &device,
+            &pipeline_layout,
+            &module,
+            Self::OFFSCREEN_FORMAT,
+        );
+        let offscreen_texture = Self::create_offscreen_texture(&device, size);
+        let state = hash_u32(descriptor.seed);
+        let simulation = Self {
+            presentation: None,
             instance,
-            surface,
-            surface_format,
-            surface_present_mode,
+            adapter,
             device,
             queue,
+            size,
             world_buffer,
             cursor_buffer,
+            view_buffer,
+            activities_buffer,
             cells_buffer_size,
             cells_input_buffer,
             cells_output_buffer,
+            temperatures_buffer_size,
+            temperatures_input_buffer,
+            temperatures_output_buffer,
+            bind_group_layout,
             bind_group,
+            module,
+            pipeline_layout,
             cursor_pipeline,
+            heat_pipeline,
+            life_pipeline,
             step_pipeline,
-            render_pipeline,
+            activity_pipeline,
+            offscreen_pipeline,
+            offscreen_texture,
+            camera: Camera::default(),
+            overlay: Overlay::default(),
+            mode: descriptor.mode,
             state,
+        };
+        simulation.write_view(true);
+        let ambient_temperature = element::temperature(element::AMBIENT_TEMPERATURE);
+        simulation.write_temperatures(&vec![ambient_temperature; (size[0] * size[1]) as usize]);
+        Ok(simulation)
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    pub fn resize(&mut self, size: [u32; 2], anchor: [Anchor; 2]) -> Result<(), SizeError> {
+        Self::check_size(&self.device, size)?;
+        let cells = backend::resize_grid(&self.read_cells(), self.size, size, anchor);
+        let temperatures = backend::resize_grid(&self.read_temperatures(), self.size, size, anchor);
+        let (cells_buffer_size, cells_input_buffer, cells_output_buffer) =
+            Self::create_cells_buffers(&self.device, size);
+        let (temperatures_buffer_size, temperatures_input_buffer, temperatures_output_buffer) =
+            Self::create_temperatures_buffers(&self.device, size);
+        let activities_buffer = Self::create_activities_buffer(&self.device, size);
+        self.bind_group = Self::create_bind_group(
+            &self.device,
+            &self.bind_group_layout,
+            &[
+                &self.world_buffer,
+                &self.cursor_buffer,
+                &cells_input_buffer,
+                &cells_output_buffer,
+                &temperatures_input_buffer,
+                &temperatures_output_buffer,
+                &self.view_buffer,
+                &activities_buffer,
+            ],
+        );
+        self.size = size;
+        self.cells_buffer_size = cells_buffer_size;
+        self.cells_input_buffer = cells_input_buffer;
+        self.cells_output_buffer = cells_output_buffer;
+        self.temperatures_buffer_size = temperatures_buffer_size;
+        self.temperatures_input_buffer = temperatures_input_buffer;
+        self.temperatures_output_buffer = temperatures_output_buffer;
+        self.activities_buffer = activities_buffer;
+        self.offscreen_texture = Self::create_offscreen_texture(&self.device, size);
+        let world = World { size };
+        self.queue
+            .write_buffer(&self.world_buffer, 0, bytemuck::bytes_of(&world));
+        self.write_cells(&cells);
+        self.write_temperatures(&temperatures);
+        self.set_camera(self.camera);
+        Ok(())
+    }
+
+    // The cells and activities are the largest storage buffers, and every dimension is both a
+    // dispatch size and a texture size.
+    fn check_size(device: &wgpu::Device, size: [u32; 2]) -> Result<(), SizeError> {
+        let limits = device.limits();
+        let max_length = limits
+            .max_texture_dimension_2d
+            .min(limits.max_compute_workgroups_per_dimension);
+        let cells_size = size[0] as u64 * size[1] as u64 * mem::size_of::<Cell>() as u64;
+        let max_cells_size =
+            (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+        if size.contains(&0)
+            || size.iter().any(|&length| length > max_length)
+            || cells_size > max_cells_size
+        {
+            return Err(SizeError(size));
         }
+        Ok(())
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// Shows a region of the world in the window, clamped so it stays inside the world.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera.clamped(self.size);
+        self.write_view(true);
+    }
+
+    pub fn overlay(&self) -> Overlay {
+        self.overlay
+    }
+
+    pub fn set_overlay(&mut self, overlay: Overlay) {
+        self.overlay = overlay;
+        self.write_view(true);
+    }
+
+    fn write_view(&self, cursor_visible: bool) {
+        let view = View {
+            offset: self.camera.offset,
+            zoom: self.camera.zoom,
+            overlay: self.overlay as u32,
+            cursor_visible: cursor_visible.into(),
+            _p0: 0,
+        };
+        self.queue
+            .write_buffer(&self.view_buffer, 0, bytemuck::bytes_of(&view));
+    }
+
+    pub fn attach(&mut self, window: Rc<Window>) {
+        let surface = Self::create_surface(&self.instance, &window);
+        self.attach_surface(window, surface);
+    }
+
+    pub fn detach(&mut self) -> Option<Rc<Window>> {
+        let presentation = self.presentation.take()?;
+        Some(presentation.window)
+    }
+
+    pub fn window(&self) -> Option<&Rc<Window>> {
+        self.presentation
+            .as_ref()
+            .map(|presentation| &presentation.window)
+    }
+
+    pub fn offscreen_texture(&self) -> &wgpu::Texture {
+        &self.offscreen_texture
+    }
+
+    fn attach_surface(&mut self, window: Rc<Window>, surface: wgpu::Surface<'static>) {
+        let capabilities = surface.get_capabilities(&self.adapter);
+        let surface_format = *capabilities.formats.first().expect("texture format");
+        let surface_present_mode = wgpu::PresentMode::AutoNoVsync;
+        let surface_config =
+            Self::create_surface_config(surface_format, window.inner_size(), surface_present_mode);
+        surface.configure(&self.device, &surface_config);
+        let render_pipeline = Self::create_render_pipeline(
+            &self.device,
+            &self.pipeline_layout,
+            &self.module,
+            surface_format,
+        );
+        self.presentation = Some(Presentation {
+            window,
+            surface,
+            surface_format,
+            surface_present_mode,
+            render_pipeline,
+        });
     }
 
     pub fn reconfigure(&self) {
-        let size = self.window.inner_size();
+        let Some(presentation) = &self.presentation else {
+            return;
+        };
+        let size = presentation.window.inner_size();
         if size.width == 0 || size.height == 0 {
             return;
         }
-        let surface_config =
-            Self::create_surface_config(self.surface_format, size, self.surface_present_mode);
-        self.surface.configure(&self.device, &surface_config);
+        let surface_config = Self::create_surface_config(
+            presentation.surface_format,
+            size,
+            presentation.surface_present_mode,
+        );
+        presentation
+            .surface
+            .configure(&self.device, &surface_config);
     }
 
-    pub fn set_cursor(&self, enabled: bool, radius: u32, position: [u32; 2], cell_id: CellId) {
+    pub fn set_cursor(&self, enabled: bool, radius: u32, position: [u32; 2], brush: Brush) {
+        let (mode, cell_id, temperature) = match brush {
+            Brush::Cell(cell_id) => (Cursor::MODE_CELL, cell_id, 0),
+            Brush::Temperature(celsius) => (
+                Cursor::MODE_TEMPERATURE,
+                CellId::Void,
+                element::temperature(celsius),
+            ),
+        };
         let cursor = Cursor {
             enabled: enabled.into(),
             radius,
             position,
             cell_id: cell_id as u32,
+            mode,
+            temperature,
             _p0: 0,
         };
         self.queue
@@ -314,22 +644,47 @@ impl Simulation {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
         {
+            let push = Push {
+                state: hash_u32(self.state),
+                ..Default::default()
+            };
             let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
             pass.set_bind_group(0, &self.bind_group, &[]);
             pass.set_pipeline(&self.cursor_pipeline);
-            pass.dispatch_workgroups(Self::SIZE[0], Self::SIZE[1], 1);
+            pass.set_push_constants(0, bytemuck::bytes_of(&push));
+            pass.dispatch_workgroups(self.size[0], self.size[1], 1);
         }
         self.queue.submit(Some(encoder.finish()));
     }
 
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
     pub fn step(&mut self) {
-        let workgroups = [
-            wgpu::util::align_to(Self::SIZE[0], 3) / 3,
-            wgpu::util::align_to(Self::SIZE[1], 3) / 3,
-        ];
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        if let Some(rule) = self.mode.life_rule() {
+            self.encode_life(&mut encoder, rule);
+        }
+        if self.mode.sand() {
+            self.encode_sand(&mut encoder);
+        }
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.set_pipeline(&self.activity_pipeline);
+            pass.dispatch_workgroups(self.size[0], self.size[1], 1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    fn encode_life(&mut self, encoder: &mut wgpu::CommandEncoder, rule: Rule) {
         encoder.copy_buffer_to_buffer(
             &self.cells_output_buffer,
             0,
@@ -337,38 +692,252 @@ impl Simulation {
             0,
             self.cells_buffer_size,
         );
-        encoder.clear_buffer(&self.cells_output_buffer, 0, None);
-        {
-            let mut push = Push {
-                local_offset: [0, 0],
-                state: self.state,
-                _p0: 0,
-            };
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        let push = Push {
+            state: hash_u32(self.state),
+            life_rule: [rule.birth, rule.survival],
+            ..Default::default()
+        };
+        pass.set_pipeline(&self.life_pipeline);
+        pass.set_push_constants(0, bytemuck::bytes_of(&push));
+        pass.dispatch_workgroups(self.size[0], self.size[1], 1);
+        self.state = self.state.wrapping_add(1);
+    }
+
+    fn encode_sand(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let workgroups = [
+            wgpu::util::align_to(self.size[0], 3) / 3,
+            wgpu::util::align_to(self.size[1], 3) / 3,
+        ];
+        encoder.copy_buffer_to_buffer(
+            &self.temperatures_output_buffer,
+            0,
+            &self.temperatures_input_buffer,
+            0,
+            self.temperatures_buffer_size,
+        );
+        for substep in 0..cell::SUBSTEPS {
+            let pairing = hash_u32(self.state);
+            encoder.copy_buffer_to_buffer(
+                &self.cells_output_buffer,
+                0,
+                &self.cells_input_buffer,
+                0,
+                self.cells_buffer_size,
+            );
+            encoder.clear_buffer(&self.cells_output_buffer, 0, None);
             let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
             pass.set_bind_group(0, &self.bind_group, &[]);
+            if substep == 0 {
+                let push = Push {
+                    state: hash_u32(self.state),
+                    ..Default::default()
+                };
+                pass.set_pipeline(&self.heat_pipeline);
+                pass.set_push_constants(0, bytemuck::bytes_of(&push));
+                pass.dispatch_workgroups(self.size[0], self.size[1], 1);
+            }
             pass.set_pipeline(&self.step_pipeline);
             for i in 0..9 {
-                push.local_offset = [i % 3, i / 3];
+                let push = Push {
+                    local_offset: [i % 3, i / 3],
+                    state: hash_u32(self.state),
+                    substep,
+                    pairing,
+                    ..Default::default()
+                };
                 pass.set_push_constants(0, bytemuck::bytes_of(&push));
                 pass.dispatch_workgroups(workgroups[0], workgroups[1], 1);
-                push.state = self.state.wrapping_add(1);
+                self.state = self.state.wrapping_add(1);
             }
-            self.state = push.state;
         }
-        self.queue.submit(Some(encoder.finish()));
+    }
+
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: u32) {
+        self.state = state;
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = File::create(path)?;
+        Snapshot::capture(self).write(BufWriter::new(file))
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let file = File::open(path)?;
+        Snapshot::read(BufReader::new(file))?.restore(self)?;
+        Ok(())
+    }
+
+    pub fn export_png(&self, path: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        let file = File::create(path)?;
+        image::export(self, BufWriter::new(file))
+    }
+
+    pub fn import_png(
+        &mut self,
+        path: impl AsRef<Path>,
+        options: &ImportOptions,
+    ) -> Result<(), ImportError> {
+        let file = File::open(path).map_err(png::DecodingError::from)?;
+        image::import(self, BufReader::new(file), options)
+    }
+
+    pub fn read_cells(&self) -> Vec<Cell> {
+        let bytes = self.read_buffer(&self.cells_output_buffer, self.cells_buffer_size);
+        Self::decode_cells(&bytes, self.size)
+    }
+
+    pub fn read_cells_async(&self, callback: impl FnOnce(Vec<Cell>) + Send + 'static) {
+        let size = self.size;
+        let staging_buffer = Arc::new(
+            self.copy_buffer_to_staging(&self.cells_output_buffer, self.cells_buffer_size),
+        );
+        let mapped_buffer = staging_buffer.clone();
+        staging_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if let Err(err) = result {
+                    log::warn!("failed to map cells staging buffer! {:?}", &err);
+                    return;
+                }
+                let cells = Self::decode_cells(&mapped_buffer.slice(..).get_mapped_range(), size);
+                mapped_buffer.unmap();
+                callback(cells);
+            });
+    }
+
+    pub fn write_cells(&self, cells: &[Cell]) {
+        assert_eq!(
+            cells.len(),
+            (self.size[0] * self.size[1]) as usize,
+            "cell count must match world size"
+        );
+        let raw_cells: Vec<[u32; 3]> = cells
+            .iter()
+            .map(|cell| [cell.id as u32, cell.state, cell.velocity])
+            .collect();
+        self.queue.write_buffer(
+            &self.cells_output_buffer,
+            0,
+            bytemuck::cast_slice(&raw_cells),
+        );
+    }
+
+    pub fn read_temperatures(&self) -> Vec<i32> {
+        let bytes = self.read_buffer(
+            &self.temperatures_output_buffer,
+            self.temperatures_buffer_size,
+        );
+        bytemuck::cast_slice::<u8, i32>(&bytes)
+            .iter()
+            .take((self.size[0] * self.size[1]) as usize)
+            .copied()
+            .collect()
+    }
+
+    pub fn write_temperatures(&self, temperatures: &[i32]) {
+        assert_eq!(
+            temperatures.len(),
+            (self.size[0] * self.size[1]) as usize,
+            "temperature count must match world size"
+        );
+        self.queue.write_buffer(
+            &self.temperatures_output_buffer,
+            0,
+            bytemuck::cast_slice(temperatures),
+        );
+    }
+
+    pub fn poll(&self) {
+        self.device.poll(wgpu::Maintain::Poll);
     }
 
     pub fn redraw(&self) {
-        let surface_texture = match self.surface.get_current_texture() {
+        let Some(presentation) = &self.presentation else {
+            self.render(&self.offscreen_pipeline, &self.offscreen_texture);
+            return;
+        };
+        let surface_texture = match presentation.surface.get_current_texture() {
             Ok(surface_texture) => surface_texture,
             Err(err) => {
                 log::warn!("failed to get surface texture! {:?}", &err);
                 return;
             }
         };
-        let view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.render(&presentation.render_pipeline, &surface_texture.texture);
+        surface_texture.present();
+    }
+
+    /// Picks an output size for `screenshot` that draws every visible cell as a `scale` by `scale`
+    /// square of pixels, as far as the device's texture size limit allows.
+    pub fn screenshot_size(&self, scale: u32) -> [u32; 2] {
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        self.camera
+            .view_size(self.size)
+            .map(|length| ((length * scale as f32).round() as u32).clamp(1, max_size))
+    }
+
+    /// Renders the current frame, as the window shows it but without the brush outline, at `size`
+    /// pixels. Returns the RGBA pixels row by row from the top, in sRGB like the window. Works
+    /// without a window too.
+    pub fn screenshot(&self, size: [u32; 2]) -> Vec<u8> {
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        assert!(
+            size.iter().all(|&length| (1..=max_size).contains(&length)),
+            "screenshot size must be between 1 and {max_size} pixels"
+        );
+        let texture = Self::create_offscreen_texture(&self.device, size);
+        self.write_view(false);
+        self.render(&self.offscreen_pipeline, &texture);
+        self.write_view(true);
+        let row_size = size[0] * 4;
+        let padded_row_size = wgpu::util::align_to(row_size, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Staging"),
+            size: padded_row_size as u64 * size[1] as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &staging_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_size),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+        self.read_staging_buffer(&staging_buffer)
+            .chunks_exact(padded_row_size as usize)
+            .flat_map(|row| &row[..row_size as usize])
+            .copied()
+            .collect()
+    }
+
+    pub fn save_screenshot(
+        &self,
+        path: impl AsRef<Path>,
+        size: [u32; 2],
+    ) -> Result<(), png::EncodingError> {
+        let pixels = self.screenshot(size);
+        let file = File::create(path)?;
+        image::write_rgba(BufWriter::new(file), size, &pixels)
+    }
+
+    fn render(&self, render_pipeline: &wgpu::RenderPipeline, texture: &wgpu::Texture) {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
@@ -388,11 +957,232 @@ impl Simulation {
                 occlusion_query_set: None,
             });
             pass.set_bind_group(0, &self.bind_group, &[]);
-            pass.set_pipeline(&self.render_pipeline);
+            pass.set_pipeline(render_pipeline);
             pass.draw(0..6, 0..1);
         }
         self.queue.submit(Some(encoder.finish()));
-        surface_texture.present();
+    }
+
+    fn read_buffer(&self, buffer: &wgpu::Buffer, size: u64) -> Vec<u8> {
+        let staging_buffer = self.copy_buffer_to_staging(buffer, size);
+        self.read_staging_buffer(&staging_buffer)
+    }
+
+    fn read_staging_buffer(&self, staging_buffer: &wgpu::Buffer) -> Vec<u8> {
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("map callback")
+            .expect("map staging buffer");
+        let bytes = slice.get_mapped_range().to_vec();
+        staging_buffer.unmap();
+        bytes
+    }
+
+    fn copy_buffer_to_staging(&self, buffer: &wgpu::Buffer, size: u64) -> wgpu::Buffer {
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Staging"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+        staging_buffer
+    }
+
+    fn decode_cells(bytes: &[u8], size: [u32; 2]) -> Vec<Cell> {
+        bytemuck::cast_slice::<u8, [u32; 3]>(bytes)
+            .iter()
+            .take((size[0] * size[1]) as usize)
+            .map(|&[id, state, velocity]| Cell {
+                id: CellId::try_from(id).expect("valid cell id"),
+                state,
+                velocity,
+            })
+            .collect()
+    }
+
+    fn shader_source() -> String {
+        format!(
+            "{}\n{}\n{}",
+            element::shader_definitions(),
+            Self::shader_definitions(),
+            include_str!("simulation.wgsl")
+        )
+    }
+
+    // The values of the uniforms that `simulation.wgsl` compares against.
+    fn shader_definitions() -> String {
+        let mut source = String::new();
+        for (name, value) in [
+            ("CURSOR_MODE_CELL", Cursor::MODE_CELL),
+            ("CURSOR_MODE_TEMPERATURE", Cursor::MODE_TEMPERATURE),
+        ] {
+            let _ = writeln!(source, "const {name}: u32 = {value}u;");
+        }
+        for overlay in Overlay::ALL {
+            let _ = writeln!(
+                source,
+                "const {}: u32 = {}u;",
+                overlay.shader_name(),
+                overlay as u32,
+            );
+        }
+        source
+    }
+
+    fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            // `WGPU_BACKEND` (e.g. `vulkan`, `dx12`, `metal`, `gl`) narrows down the backends to try.
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+            ..Default::default()
+        })
+    }
+
+    fn create_surface(instance: &wgpu::Instance, window: &Window) -> wgpu::Surface<'static> {
+        let target = unsafe { wgpu::SurfaceTargetUnsafe::from_window(window) }
+            .expect("valid surface target");
+        unsafe { instance.create_surface_unsafe(target) }.expect("new surface")
+    }
+
+    fn create_cells_buffers(
+        device: &wgpu::Device,
+        size: [u32; 2],
+    ) -> (u64, wgpu::Buffer, wgpu::Buffer) {
+        let cells_buffer_size = wgpu::util::align_to(
+            mem::size_of::<Cell>() as u64 * (size[0] * size[1]) as u64,
+            wgpu::COPY_BUFFER_ALIGNMENT,
+        );
+        let cells_input_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cells Input"),
+            size: cells_buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let cells_output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cells Output"),
+            size: cells_buffer_size,
+            usage: wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        (cells_buffer_size, cells_input_buffer, cells_output_buffer)
+    }
+
+    fn create_temperatures_buffers(
+        device: &wgpu::Device,
+        size: [u32; 2],
+    ) -> (u64, wgpu::Buffer, wgpu::Buffer) {
+        let temperatures_buffer_size = wgpu::util::align_to(
+            mem::size_of::<i32>() as u64 * (size[0] * size[1]) as u64,
+            wgpu::COPY_BUFFER_ALIGNMENT,
+        );
+        let temperatures_input_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Temperatures Input"),
+            size: temperatures_buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let temperatures_output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Temperatures Output"),
+            size: temperatures_buffer_size,
+            usage: wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        (
+            temperatures_buffer_size,
+            temperatures_input_buffer,
+            temperatures_output_buffer,
+        )
+    }
+
+    fn create_activities_buffer(device: &wgpu::Device, size: [u32; 2]) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Activities"),
+            size: mem::size_of::<Activity>() as u64 * (size[0] * size[1]) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    // `buffers` are bound in order, starting at binding 0.
+    fn create_bind_group(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        buffers: &[&wgpu::Buffer],
+    ) -> wgpu::BindGroup {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: bind_group_layout,
+            entries: &entries,
+        })
+    }
+
+    fn create_offscreen_texture(device: &wgpu::Device, size: [u32; 2]) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::OFFSCREEN_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        pipeline_layout: &wgpu::PipelineLayout,
+        module: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: "vertex_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module,
+                entry_point: "fragment_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
     }
 
     fn create_surface_config(
@@ -412,3 +1202,53 @@ impl Simulation {
         }
     }
 }
+
+impl Backend for Simulation {
+    fn size(&self) -> [u32; 2] {
+        Simulation::size(self)
+    }
+
+    fn resize(&mut self, size: [u32; 2], anchor: [Anchor; 2]) -> Result<(), SizeError> {
+        Simulation::resize(self, size, anchor)
+    }
+
+    fn set_cursor(&mut self, enabled: bool, radius: u32, position: [u32; 2], brush: Brush) {
+        Simulation::set_cursor(self, enabled, radius, position, brush);
+    }
+
+    fn mode(&self) -> Mode {
+        Simulation::mode(self)
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        Simulation::set_mode(self, mode);
+    }
+
+    fn step(&mut self) {
+        Simulation::step(self);
+    }
+
+    fn state(&self) -> u32 {
+        Simulation::state(self)
+    }
+
+    fn set_state(&mut self, state: u32) {
+        Simulation::set_state(self, state);
+    }
+
+    fn read_cells(&self) -> Vec<Cell> {
+        Simulation::read_cells(self)
+    }
+
+    fn write_cells(&mut self, cells: &[Cell]) {
+        Simulation::write_cells(self, cells);
+    }
+
+    fn read_temperatures(&self) -> Vec<i32> {
+        Simulation::read_temperatures(self)
+    }
+
+    fn write_temperatures(&mut self, temperatures: &[i32]) {
+        Simulation::write_temperatures(self, temperatures);
+    }
+}
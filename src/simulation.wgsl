@@ -7,23 +7,40 @@ struct Cursor {
     radius: u32,
     position: vec2<u32>,
     cell_id: u32,
+    mode: u32,
+    temperature: i32,
+}
+
+struct View {
+    offset: vec2<f32>,
+    zoom: f32,
+    overlay: u32,
+    cursor_visible: u32,
 }
 
 struct Cell {
     id: u32,
     state: u32,
+    velocity: u32,
+}
+
+// Only feeds the debug overlays. `key` is the id and shade of the cell at the end of the last
+// step, `heat` fades out after the cell changes and `pass_index` is the checkerboard pass that
+// last updated the cell.
+struct Activity {
+    key: u32,
+    heat: u32,
+    pass_index: u32,
 }
 
 struct Push {
     local_offset: vec2<u32>,
     state: u32,
+    substep: u32,
+    life_rule: vec2<u32>,
+    pairing: u32,
 }
 
-const CELL_ID_VOID: u32 = 0x00u;
-const CELL_ID_ROCK: u32 = 0x01u;
-const CELL_ID_SAND: u32 = 0x02u;
-const CELL_ID_WATER: u32 = 0x03u;
-
 @group(0) @binding(0)
 var<uniform> world: World;
 @group(0) @binding(1)
@@ -32,8 +49,18 @@ var<uniform> cursor: Cursor;
 var<storage, read_write> cells_input: array<Cell>;
 @group(0) @binding(3)
 var<storage, read_write> cells_output: array<Cell>;
+@group(0) @binding(4)
+var<storage, read_write> temperatures_input: array<i32>;
+@group(0) @binding(5)
+var<storage, read_write> temperatures_output: array<i32>;
+@group(0) @binding(6)
+var<uniform> view: View;
+@group(0) @binding(7)
+var<storage, read_write> activities: array<Activity>;
 var<push_constant> push: Push;
 
+const ACTIVITY_HEAT: u32 = 255u;
+
 fn hash_u32(value: u32) -> u32 {
     var x = value;
     x += (x << 10u);
@@ -65,33 +92,459 @@ fn cell_index(position: vec2<u32>) -> u32 {
     return position.y * world.size.x + position.x;
 }
 
+var<private> neighbour_offsets: array<vec2<u32>, 8> = array<vec2<u32>, 8>(
+    vec2(4294967295u, 4294967295u),
+    vec2(0u, 4294967295u),
+    vec2(1u, 4294967295u),
+    vec2(4294967295u, 0u),
+    vec2(1u, 0u),
+    vec2(4294967295u, 1u),
+    vec2(0u, 1u),
+    vec2(1u, 1u),
+);
+
+var<private> pairing_offsets: array<vec2<u32>, 4> = array<vec2<u32>, 4>(
+    vec2(1u, 0u),
+    vec2(0u, 1u),
+    vec2(1u, 1u),
+    vec2(1u, 4294967295u),
+);
+
+fn cell_spawn(id: u32, random: u32) -> Cell {
+    let shade = random & ~STATE_MASK;
+    let lifetime = cell_lifetimes[id];
+    if lifetime != 0u {
+        return Cell(id, shade | (lifetime / 2u + random % (lifetime / 2u + 1u)), 0u);
+    }
+    if cell_growths[id] != 0u {
+        return Cell(id, shade | cell_growths[id], 0u);
+    }
+    return Cell(id, random, 0u);
+}
+
 @compute @workgroup_size(1, 1, 1)
 fn compute_cursor(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let position = global_id.xy;
     if !world_contains(position) || cursor.enabled == 0u || !cursor_contains(position) {
         return;
     }
-    let state = hash_vec2_u32(cursor.position) ^ hash_vec2_u32(position);
-    cells_output[cell_index(position)] = Cell(cursor.cell_id, state);
+    let index = cell_index(position);
+    if cursor.mode == CURSOR_MODE_TEMPERATURE {
+        temperatures_output[index] = cursor.temperature;
+        return;
+    }
+    let state = push.state ^ hash_vec2_u32(cursor.position) ^ hash_vec2_u32(position);
+    cells_output[index] = cell_spawn(cursor.cell_id, state);
+    temperatures_output[index] = cell_temperatures[cursor.cell_id];
 }
 
-fn cell_compare(position: vec2<u32>, id: u32) -> bool {
+// Each neighbour pair exchanges the same truncated amount in opposite directions, so heat is
+// conserved apart from what void absorbs. Conductivities are at most 256, so a pair moves at most
+// a quarter of its difference per step, which keeps the diffusion stable.
+@compute @workgroup_size(1, 1, 1)
+fn compute_heat(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let position = global_id.xy;
+    if !world_contains(position) {
+        return;
+    }
+    let index = cell_index(position);
+    let id = cells_input[index].id;
+    let ambient_temperature = cell_temperatures[CELL_ID_VOID];
+    if id == CELL_ID_VOID {
+        temperatures_output[index] = ambient_temperature;
+        return;
+    }
+    let temperature = temperatures_input[index];
+    var heat = 0;
+    var heat_positions = array(
+        position - vec2(1u, 0u),
+        position + vec2(1u, 0u),
+        position - vec2(0u, 1u),
+        position + vec2(0u, 1u),
+    );
+    for (var i = 0u; i < 4u; i++) {
+        let neighbour_position = heat_positions[i];
+        if !world_contains(neighbour_position) {
+            continue;
+        }
+        let neighbour_index = cell_index(neighbour_position);
+        let neighbour_id = cells_input[neighbour_index].id;
+        var neighbour_temperature = temperatures_input[neighbour_index];
+        if neighbour_id == CELL_ID_VOID {
+            neighbour_temperature = ambient_temperature;
+        }
+        let conductivity = min(cell_conductivities[id], cell_conductivities[neighbour_id]);
+        heat += (conductivity * (neighbour_temperature - temperature)) / 1024;
+    }
+    temperatures_output[index] = temperature + heat;
+}
+
+// Unlike `compute_step`, every cell reads only the previous generation and writes only itself, so
+// a generation is updated synchronously. `push.life_rule` holds the birth and survival bit masks.
+@compute @workgroup_size(1, 1, 1)
+fn compute_life(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let position = global_id.xy;
+    if !world_contains(position) {
+        return;
+    }
+    let index = cell_index(position);
+    let cell = cells_input[index];
+    var live_neighbours = 0u;
+    for (var i = 0u; i < 8u; i++) {
+        let neighbour_position = position + neighbour_offsets[i];
+        if world_contains(neighbour_position) && cells_input[cell_index(neighbour_position)].id == CELL_ID_LIFE {
+            live_neighbours++;
+        }
+    }
+    let random = hash_u32(push.state ^ hash_vec2_u32(position));
+    if cell.id == CELL_ID_LIFE && (push.life_rule.y & (1u << live_neighbours)) == 0u {
+        cells_output[index] = Cell(CELL_ID_VOID, random, 0u);
+        return;
+    }
+    if cell.id == CELL_ID_VOID && (push.life_rule.x & (1u << live_neighbours)) != 0u {
+        cells_output[index] = cell_spawn(CELL_ID_LIFE, random);
+        temperatures_output[index] = cell_temperatures[CELL_ID_LIFE];
+        return;
+    }
+    cells_output[index] = cell;
+}
+
+fn cell_key(cell: Cell) -> u32 {
+    return (cell.state & ~STATE_MASK) | cell.id;
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn compute_activity(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let position = global_id.xy;
+    if !world_contains(position) {
+        return;
+    }
     let index = cell_index(position);
-    return cells_input[index].id == id && cells_output[index].id == id;
+    let key = cell_key(cells_output[index]);
+    var activity = activities[index];
+    if key != activity.key {
+        activity.heat = ACTIVITY_HEAT;
+    }
+    else {
+        activity.heat = activity.heat * 7u / 8u;
+    }
+    activity.key = key;
+    activities[index] = activity;
+}
+
+fn cell_processed(position: vec2<u32>) -> bool {
+    let pass_index = push.local_offset.x + push.local_offset.y * 3u;
+    return (position.x % 3u) + (position.y % 3u) * 3u < pass_index;
+}
+
+fn cell_current(position: vec2<u32>) -> Cell {
+    let index = cell_index(position);
+    if cell_processed(position) || cells_output[index].id != CELL_ID_VOID {
+        return cells_output[index];
+    }
+    return cells_input[index];
 }
 
-fn cell_swap(from_position: vec2<u32>, to_position: vec2<u32>) {
+// Movers displace void and lighter movers, except that gases only rise through denser powders
+// and liquids. Static cells are never displaced.
+fn cell_compare(position: vec2<u32>, id: u32) -> bool {
+    let other_id = cell_current(position).id;
+    let other_behaviour = cell_behaviours[other_id];
+    if other_id == CELL_ID_VOID {
+        return true;
+    }
+    if other_behaviour == BEHAVIOUR_STATIC {
+        return false;
+    }
+    if cell_behaviours[id] == BEHAVIOUR_GAS {
+        return other_behaviour != BEHAVIOUR_GAS && cell_densities[other_id] > cell_densities[id];
+    }
+    return cell_densities[other_id] < cell_densities[id];
+}
+
+fn cell_write(index: u32, cell: Cell) {
+    cells_output[index] = cell;
+    activities[index].pass_index = push.local_offset.x + push.local_offset.y * 3u;
+}
+
+// Displaced cells lose their velocity, only the moving cell keeps falling.
+fn cell_swap(from_position: vec2<u32>, from_cell: Cell, to_position: vec2<u32>) {
     let from_index = cell_index(from_position);
     var s = push.state;
-    var from_cell = cells_input[from_index];
+    var moved_cell = from_cell;
     s ^= hash_vec2_u32(to_position);
-    from_cell.state ^= s;
+    if !cell_keeps_state[moved_cell.id] {
+        moved_cell.state ^= s & STATE_MASK;
+    }
     let to_index = cell_index(to_position);
-    var to_cell = cells_input[to_index];
+    var to_cell = cell_current(to_position);
+    var to_temperature = temperatures_output[to_index];
+    // A cell pushed aside before its own pass still reacts with its partner, or the partner would
+    // react alone.
+    if push.substep == 0u && !cell_processed(to_position) && cells_output[to_index].id == CELL_ID_VOID {
+        let product = cell_reaction(to_position, to_cell.id);
+        if product != to_cell.id {
+            to_cell = cell_spawn(product, hash_u32(push.state ^ hash_vec2_u32(to_position)));
+            to_temperature = cell_temperatures[product];
+        }
+    }
     s ^= hash_vec2_u32(from_position);
-    to_cell.state ^= s;
-    cells_output[to_index] = from_cell;
-    cells_output[from_index] = to_cell;
+    if !cell_keeps_state[to_cell.id] {
+        to_cell.state ^= s & STATE_MASK;
+    }
+    to_cell.velocity = 0u;
+    cell_write(to_index, moved_cell);
+    cell_write(from_index, to_cell);
+    temperatures_output[to_index] = temperatures_output[from_index];
+    temperatures_output[from_index] = to_temperature;
+}
+
+fn cell_ignited(position: vec2<u32>, id: u32, random: u32) -> bool {
+    let flammability = cell_flammabilities[id];
+    if flammability == 0u || (random & 0xffffu) >= flammability {
+        return false;
+    }
+    for (var i = 0u; i < 8u; i++) {
+        let neighbour_position = position + neighbour_offsets[i];
+        if world_contains(neighbour_position) && cell_igniters[cells_input[cell_index(neighbour_position)].id] {
+            return true;
+        }
+    }
+    return false;
+}
+
+// `push.pairing` stays the same for every pass of a step and picks an axis and the side of it
+// that cells with an even coordinate along it pair with. Moving one cell along the axis flips
+// that coordinate's parity, so every cell is the partner of its own partner.
+fn cell_partner(position: vec2<u32>) -> vec2<u32> {
+    let axis = push.pairing % 4u;
+    let offset = pairing_offsets[axis];
+    let coordinate = select(position.x, position.y, axis == 1u);
+    if ((coordinate ^ (push.pairing >> 2u)) & 1u) == 0u {
+        return position + offset;
+    }
+    return position - offset;
+}
+
+// Returns what the cell turns into by reacting with its partner, or its own id if they do not
+// react. Reactions only read the input cells and only write the reacting cell, so both cells of a
+// pair turn into their products no matter which pass processes them first, and a cell reacts with
+// at most one neighbour per step.
+fn cell_reaction(position: vec2<u32>, id: u32) -> u32 {
+    let partner_position = cell_partner(position);
+    if !world_contains(partner_position) {
+        return id;
+    }
+    let partner_id = cells_input[cell_index(partner_position)].id;
+    if partner_id == CELL_ID_VOID {
+        return id;
+    }
+    if cell_reacts_with[id] == partner_id {
+        return cell_reacts_into[id];
+    }
+    if cell_reacts_with[partner_id] == id {
+        return cell_reactant_into[partner_id];
+    }
+    return id;
+}
+
+fn cell_pair_random(position: vec2<u32>, neighbour_position: vec2<u32>) -> u32 {
+    let state = cells_input[cell_index(position)].state ^ cells_input[cell_index(neighbour_position)].state;
+    return hash_u32(state ^ hash_vec2_u32(position) ^ hash_vec2_u32(neighbour_position));
+}
+
+// Both cells of a pair roll the same number, so a corrosive cell is only used up in a step where
+// a neighbour rolls to dissolve too. It may dissolve several neighbours in that step, and a
+// neighbour that changes some other way first, like sand soaking up water, still uses it up.
+fn cell_dissolved(position: vec2<u32>, id: u32) -> bool {
+    let corrosive = cell_corrosives[id];
+    for (var i = 0u; i < 8u; i++) {
+        let neighbour_position = position + neighbour_offsets[i];
+        if !world_contains(neighbour_position) {
+            continue;
+        }
+        let neighbour_id = cells_input[cell_index(neighbour_position)].id;
+        if corrosive == cell_corrosives[neighbour_id] {
+            continue;
+        }
+        let dissolve_chance = select(cell_dissolve_chances[id], cell_dissolve_chances[neighbour_id], corrosive);
+        if (cell_pair_random(position, neighbour_position) & 0xffffu) < dissolve_chance {
+            return true;
+        }
+    }
+    return false;
+}
+
+fn cell_germinates(position: vec2<u32>, id: u32) -> bool {
+    if cell_germinates_into[id] == CELL_ID_VOID {
+        return false;
+    }
+    for (var i = 0u; i < 8u; i++) {
+        let neighbour_position = position + neighbour_offsets[i];
+        if world_contains(neighbour_position) && cell_wet[cells_input[cell_index(neighbour_position)].id] {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Void cells pull growth from the plant below or diagonally below them, so only the void cell is
+// written. Growing only next to exactly one growing cell keeps plants thin, and they branch when
+// two cells grow from the same tip in one step.
+fn cell_growth(position: vec2<u32>, random: u32) -> Cell {
+    var growing_neighbours = 0u;
+    for (var i = 0u; i < 8u; i++) {
+        let neighbour_position = position + neighbour_offsets[i];
+        if world_contains(neighbour_position) && cell_growths[cells_input[cell_index(neighbour_position)].id] != 0u {
+            growing_neighbours++;
+        }
+    }
+    if growing_neighbours != 1u {
+        return Cell(CELL_ID_VOID, random, 0u);
+    }
+    var growth_positions = array(
+        position - vec2(0u, 1u),
+        position - vec2(1u, 1u),
+        position + vec2(1u, 4294967295u),
+    );
+    for (var i = 0u; i < 3u; i++) {
+        let growth_position = growth_positions[i];
+        if !world_contains(growth_position) {
+            continue;
+        }
+        let neighbour = cells_input[cell_index(growth_position)];
+        let stage = neighbour.state & STATE_MASK;
+        if cell_growths[neighbour.id] == 0u || stage <= 1u {
+            continue;
+        }
+        var growth_chance = cell_growth_chances[neighbour.id];
+        if i != 0u {
+            growth_chance /= 2u;
+        }
+        if (hash_u32(random ^ i) & 0xffffu) < growth_chance {
+            return Cell(neighbour.id, (random & ~STATE_MASK) | (stage - 1u), 0u);
+        }
+    }
+    return Cell(CELL_ID_VOID, random, 0u);
+}
+
+fn cell_charged_neighbours(position: vec2<u32>) -> u32 {
+    var charged_neighbours = 0u;
+    for (var i = 0u; i < 8u; i++) {
+        let neighbour_position = position + neighbour_offsets[i];
+        if world_contains(neighbour_position) && cell_charged[cells_input[cell_index(neighbour_position)].id] {
+            charged_neighbours++;
+        }
+    }
+    return charged_neighbours;
+}
+
+// Like every other rule, conductors only read the input cells, so a whole circuit advances one
+// Wireworld generation per step.
+fn cell_powered(position: vec2<u32>, id: u32) -> bool {
+    if cell_powered_into[id] == CELL_ID_VOID {
+        return false;
+    }
+    let charged_neighbours = cell_charged_neighbours(position);
+    return charged_neighbours == 1u || charged_neighbours == 2u;
+}
+
+// Void cells pull emitted cells from a powered emitter right above them.
+fn cell_emission(position: vec2<u32>, random: u32) -> Cell {
+    let emitter_position = position + vec2(0u, 1u);
+    if !world_contains(emitter_position) {
+        return Cell(CELL_ID_VOID, random, 0u);
+    }
+    let emitted_id = cell_emits[cells_input[cell_index(emitter_position)].id];
+    if emitted_id == CELL_ID_VOID || cell_charged_neighbours(emitter_position) == 0u {
+        return Cell(CELL_ID_VOID, random, 0u);
+    }
+    return cell_spawn(emitted_id, random);
+}
+
+fn cell_fall(position: vec2<u32>, cell: Cell, state: u32) -> bool {
+    let fall_down_position = position - vec2(0u, 1u);
+    var fall_positions = array(
+        fall_down_position,
+        fall_down_position - vec2(1u, 0u),
+        fall_down_position,
+        fall_down_position + vec2(1u, 0u),
+        fall_down_position,
+    );
+    let fall_positions_array_length = 5u;
+    let fall_index_offset = state % fall_positions_array_length;
+    for (var i = 0u; i < fall_positions_array_length; i++) {
+        let fall_adjacent_position = fall_positions[(i + fall_index_offset) % fall_positions_array_length];
+        if !world_contains(fall_adjacent_position) {
+            continue;
+        }
+        else if cell_compare(fall_adjacent_position, cell.id) {
+            cell_swap(position, cell, fall_adjacent_position);
+            return true;
+        }
+    }
+    return false;
+}
+
+// Falling cells gain `acceleration` and carry their velocity along, while cells that fail to fall
+// land and scatter sideways if they were fast. Cells that neither fall nor scatter have landed and
+// must lose their velocity.
+fn cell_fall_fast(position: vec2<u32>, cell: Cell, state: u32, acceleration: u32) -> bool {
+    var falling_cell = cell;
+    falling_cell.velocity = min(cell.velocity + acceleration, MAX_VELOCITY);
+    if cell_fall(position, falling_cell, state) {
+        return true;
+    }
+    if cell.velocity < SPLASH_VELOCITY {
+        return false;
+    }
+    var landed_cell = cell;
+    landed_cell.velocity = 0u;
+    return cell_spread(position, landed_cell, state);
+}
+
+fn cell_rise(position: vec2<u32>, cell: Cell, state: u32) -> bool {
+    let rise_up_position = position + vec2(0u, 1u);
+    var rise_positions = array(
+        rise_up_position,
+        rise_up_position - vec2(1u, 0u),
+        rise_up_position,
+        rise_up_position + vec2(1u, 0u),
+        rise_up_position,
+    );
+    let rise_positions_array_length = 5u;
+    let rise_index_offset = state % rise_positions_array_length;
+    for (var i = 0u; i < rise_positions_array_length; i++) {
+        let rise_adjacent_position = rise_positions[(i + rise_index_offset) % rise_positions_array_length];
+        if !world_contains(rise_adjacent_position) {
+            continue;
+        }
+        else if cell_compare(rise_adjacent_position, cell.id) {
+            cell_swap(position, cell, rise_adjacent_position);
+            return true;
+        }
+    }
+    return false;
+}
+
+fn cell_spread(position: vec2<u32>, cell: Cell, state: u32) -> bool {
+    var spread_positions = array(
+        position - vec2(1u, 0u),
+        position + vec2(1u, 0u),
+    );
+    let spread_positions_array_length = 2u;
+    let spread_index_offset = hash_u32(state ^ hash_vec2_u32(position)) % spread_positions_array_length;
+    for (var i = 0u; i < spread_positions_array_length; i++) {
+        let spread_adjacent_position = spread_positions[(i + spread_index_offset) % spread_positions_array_length];
+        if !world_contains(spread_adjacent_position) {
+            continue;
+        }
+        else if cell_compare(spread_adjacent_position, cell.id) {
+            cell_swap(position, cell, spread_adjacent_position);
+            return true;
+        }
+    }
+    return false;
 }
 
 @compute @workgroup_size(1, 1, 1)
@@ -101,34 +554,144 @@ fn compute_step(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
     let index = cell_index(position);
-    let id = cells_input[index].id;
+    var cell = cells_input[index];
+    let id = cell.id;
     var state = push.state;
-    if id == CELL_ID_VOID {
+    if cells_output[index].id != CELL_ID_VOID {
         return;
     }
-    else if id == CELL_ID_SAND {
-        let fall_down_position = position - vec2(0u, 1u);
-        var fall_positions = array(
-            fall_down_position,
-            fall_down_position - vec2(1u, 0u),
-            fall_down_position,
-            fall_down_position + vec2(1u, 0u),
-            fall_down_position,
-        );
-        let fall_positions_array_length = 5u;
-        let fall_index_offset = state % fall_positions_array_length;
-        for (var i = 0u; i < fall_positions_array_length; i++) {
-            let fall_adjacent_position = fall_positions[(i + fall_index_offset) % fall_positions_array_length];
-            if !world_contains(fall_adjacent_position) {
-                continue;
-            }
-            else if cell_compare(fall_adjacent_position, CELL_ID_VOID) {
-                cell_swap(position, fall_adjacent_position);
+    // Every later substep only moves the cells fast enough for another cell of their fall, so a
+    // cell falling at `n` cells per step moves up to `n + 1` cells.
+    if push.substep != 0u {
+        if id == CELL_ID_VOID {
+            return;
+        }
+        if push.substep <= cell.velocity / VELOCITY_SCALE {
+            if cell_fall_fast(position, cell, state, 0u) {
                 return;
             }
+            cell.velocity = 0u;
+        }
+        cells_output[index] = cell;
+        return;
+    }
+    let random = hash_u32(state ^ hash_vec2_u32(position));
+    if id == CELL_ID_VOID {
+        var new_cell = cell_growth(position, random);
+        if new_cell.id == CELL_ID_VOID {
+            new_cell = cell_emission(position, random);
+        }
+        if new_cell.id != CELL_ID_VOID {
+            cell_write(index, new_cell);
+            temperatures_output[index] = cell_temperatures[new_cell.id];
+        }
+        return;
+    }
+    let temperature = temperatures_output[index];
+    if temperature > cell_heats_above[id] {
+        cell_write(index, cell_spawn(cell_heats_into[id], random));
+        return;
+    }
+    if temperature < cell_cools_below[id] {
+        cell_write(index, cell_spawn(cell_cools_into[id], random));
+        return;
+    }
+    let product = cell_reaction(position, id);
+    if product != id {
+        cell_write(index, cell_spawn(product, random));
+        temperatures_output[index] = cell_temperatures[product];
+        return;
+    }
+    if cell_dissolved(position, id) {
+        cell_write(index, Cell(CELL_ID_VOID, random, 0u));
+        return;
+    }
+    if cell_germinates(position, id) {
+        cell_write(index, cell_spawn(cell_germinates_into[id], random));
+        return;
+    }
+    if cell_discharges_into[id] != CELL_ID_VOID {
+        cell_write(index, cell_spawn(cell_discharges_into[id], random));
+        return;
+    }
+    if cell_powered(position, id) {
+        cell_write(index, cell_spawn(cell_powered_into[id], random));
+        return;
+    }
+    let lifetime = cell_lifetimes[id];
+    if lifetime != 0u {
+        if (cell.state & STATE_MASK) <= 1u {
+            cell_write(index, cell_spawn(cell_decays[id], random));
+            return;
+        }
+        cell.state -= 1u;
+    }
+    else if cell_ignited(position, id, random) {
+        cell_write(index, cell_spawn(CELL_ID_FIRE, random));
+        temperatures_output[index] = max(temperature, cell_temperatures[CELL_ID_FIRE]);
+        return;
+    }
+    let viscous = (random >> 16u) < cell_viscosities[id];
+    let behaviour = select(cell_behaviours[id], BEHAVIOUR_STATIC, viscous);
+    if behaviour == BEHAVIOUR_POWDER {
+        if cell_fall_fast(position, cell, state, GRAVITY) {
+            return;
+        }
+    }
+    else if behaviour == BEHAVIOUR_LIQUID {
+        if cell_fall_fast(position, cell, state, GRAVITY) {
+            return;
+        }
+        cell.velocity = 0u;
+        if cell_spread(position, cell, state) {
+            return;
+        }
+    }
+    else if behaviour == BEHAVIOUR_GAS {
+        // Moves are never chained with `||` or `&&`: naga calls both sides of them, so a gas
+        // would rise and spread at once and leave a copy of itself behind.
+        if cell_rise(position, cell, state) {
+            return;
+        }
+        if cell_spread(position, cell, state) {
+            return;
+        }
+    }
+    if !cell_keeps_state[id] {
+        cell.state = (cell.state & ~STATE_MASK) | (state & STATE_MASK);
+    }
+    cell.velocity = 0u;
+    cell_write(index, cell);
+}
+
+// Maps 0 to blue, through cyan, green and yellow, to red at 1.
+fn false_color(value: f32) -> vec3<f32> {
+    let x = 4.0 * clamp(value, 0.0, 1.0);
+    return clamp(vec3(1.5) - abs(vec3(x) - vec3(3.0, 2.0, 1.0)), vec3(0.0), vec3(1.0));
+}
+
+fn overlay_color(position: vec2<u32>, cell: Cell, color: vec3<f32>) -> vec3<f32> {
+    let index = cell_index(position);
+    switch view.overlay {
+        case OVERLAY_STATE: {
+            let state = cell.state & STATE_MASK;
+            return vec3(f32(state & 0xffu), f32((state >> 8u) & 0xffu), f32(state >> 16u)) / 255.0;
+        }
+        case OVERLAY_ACTIVITY: {
+            let heat = f32(activities[index].heat) / f32(ACTIVITY_HEAT);
+            return mix(color * 0.25, false_color(heat), heat);
+        }
+        case OVERLAY_PASS: {
+            return mix(color, false_color(f32(activities[index].pass_index) / 8.0), 0.75);
+        }
+        case OVERLAY_TEMPERATURE: {
+            let celsius = f32(temperatures_output[index]) / TEMPERATURE_SCALE;
+            return false_color((celsius + 50.0) / 1550.0);
+        }
+        default: {
+            return color;
         }
     }
-    cells_output[index] = Cell(id, state);
 }
 
 struct Vertex {
@@ -156,23 +719,32 @@ fn vertex_main(@builtin(vertex_index) vertex_index: u32) -> VertToFrag {
     );
 }
 
-var<private> cell_colors: array<vec3<f32>, 4> = array<vec3<f32>, 4>(
-    vec3(0.0, 0.0, 0.0),
-    vec3(0.4, 0.4, 0.4),
-    vec3(0.91, 0.773, 0.498),
-    vec3(0.0, 0.0, 1.0),
-);
-
 @fragment
 fn fragment_main(vert_to_frag: VertToFrag) -> @location(0) vec4<f32> {
-    let position = vec2<u32>(vert_to_frag.texture_coord * vec2<f32>(world.size));
+    let world_position = view.offset + vert_to_frag.texture_coord * vec2<f32>(world.size) / view.zoom;
+    let position = min(vec2<u32>(world_position), world.size - 1u);
     let squared_distance = cursor_squared_distance(position);
     let squared_outer_radius = cursor.radius * cursor.radius;
     let squared_inner_radius = (cursor.radius - 1) * (cursor.radius - 1);
-    let id = cells_output[cell_index(position)].id;
-    var color = cell_colors[id];
-    if squared_distance < squared_outer_radius && squared_distance >= squared_inner_radius {
-        let cursor_color = cell_colors[cursor.cell_id];
+    let cell = cells_output[cell_index(position)];
+    let shade = f32(cell.state >> SHADE_SHIFT) / f32(~STATE_MASK >> SHADE_SHIFT);
+    var color = cell_colors[cell.id] * (1.0 - cell_color_variations[cell.id] * shade);
+    let lifetime = cell_lifetimes[cell.id];
+    if lifetime != 0u {
+        color *= min(f32(cell.state & STATE_MASK) / f32(lifetime), 1.0);
+    }
+    if cell.id != CELL_ID_VOID {
+        let celsius = f32(temperatures_output[cell_index(position)]) / TEMPERATURE_SCALE;
+        let glow = clamp((celsius - 500.0) / 1000.0, 0.0, 1.0);
+        color = mix(color, vec3(1.0, 0.45, 0.1), glow);
+    }
+    color = overlay_color(position, cell, color);
+    let on_cursor = squared_distance < squared_outer_radius && squared_distance >= squared_inner_radius;
+    if view.cursor_visible != 0u && on_cursor {
+        var cursor_color = cell_colors[cursor.cell_id];
+        if cursor.mode == CURSOR_MODE_TEMPERATURE {
+            cursor_color = select(vec3(0.0, 0.5, 1.0), vec3(1.0, 0.2, 0.0), cursor.temperature > cell_temperatures[CELL_ID_VOID]);
+        }
         color = (cursor_color * 0.5) + ((vec3(1.0) - color) * 0.5);
     }
     return vec4(color, 1.0);